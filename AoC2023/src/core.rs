@@ -1,7 +1,16 @@
+pub mod aho_corasick;
+pub mod bitset;
+pub mod cycle;
 pub mod day01;
 pub mod day02;
 pub mod day03;
 pub mod day04;
 pub mod day05;
+pub mod geometry;
+pub mod graph;
 pub mod iter;
+pub mod json;
+pub mod math;
 pub mod parsers;
+#[cfg(test)]
+pub mod xorshift;
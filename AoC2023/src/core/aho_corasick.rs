@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+/// Multi-pattern matcher that finds every occurrence of every pattern in one pass over the input.
+/// The trie and its failure links are flattened into a full transition table, so each input byte
+/// costs a single lookup.
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    /// `transitions[state * 256 + byte]` is the next state
+    transitions: Vec<u32>,
+    /// Patterns ending at each state, including those reached through failure links
+    outputs: Vec<Vec<usize>>,
+    pattern_lens: Vec<usize>,
+}
+
+/// A pattern found in the input, `start..end` are byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl AhoCorasick {
+    /// Builds the matcher. Patterns are identified by their index in the slice.
+    /// Empty patterns never match.
+    pub fn new<P: AsRef<[u8]>>(patterns: &[P]) -> AhoCorasick {
+        // Build the trie, state 0 is the root
+        let mut children: Vec<[Option<u32>; 256]> = vec![[None; 256]];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+        patterns.iter().enumerate().for_each(|(idx, pattern)| {
+            let pattern = pattern.as_ref();
+            if pattern.is_empty() {
+                return;
+            }
+            let mut state = 0usize;
+            pattern.iter().for_each(|byte| {
+                state = match children[state][*byte as usize] {
+                    Some(next) => next as usize,
+                    None => {
+                        children.push([None; 256]);
+                        outputs.push(Vec::new());
+                        let next = children.len() - 1;
+                        children[state][*byte as usize] = Some(next as u32);
+                        next
+                    }
+                };
+            });
+            outputs[state].push(idx);
+        });
+
+        // Breadth first from the root so each state's failure link is final before its children need it
+        let mut transitions: Vec<u32> = vec![0; children.len() * 256];
+        let mut failures: Vec<u32> = vec![0; children.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        (0..256).for_each(|byte| {
+            if let Some(next) = children[0][byte] {
+                transitions[byte] = next;
+                queue.push_back(next as usize);
+            }
+        });
+        while let Some(state) = queue.pop_front() {
+            let failure = failures[state] as usize;
+            let inherited = outputs[failure].clone();
+            outputs[state].extend(inherited);
+            (0..256).for_each(|byte| match children[state][byte] {
+                Some(next) => {
+                    failures[next as usize] = transitions[failure * 256 + byte];
+                    transitions[state * 256 + byte] = next;
+                    queue.push_back(next as usize);
+                }
+                None => {
+                    transitions[state * 256 + byte] = transitions[failure * 256 + byte];
+                }
+            });
+        }
+
+        AhoCorasick {
+            transitions,
+            outputs,
+            pattern_lens: patterns.iter().map(|x| x.as_ref().len()).collect(),
+        }
+    }
+
+    /// Returns every match, overlapping ones included, ordered by where they end.
+    /// Matches that end at the same place are ordered longest first.
+    pub fn find_overlapping<'a>(&'a self, haystack: &'a [u8]) -> OverlappingMatches<'a> {
+        OverlappingMatches {
+            matcher: self,
+            haystack,
+            position: 0,
+            state: 0,
+            pending: 0,
+        }
+    }
+}
+
+/// Iterator returned by `AhoCorasick::find_overlapping`
+#[derive(Debug, Clone)]
+pub struct OverlappingMatches<'a> {
+    matcher: &'a AhoCorasick,
+    haystack: &'a [u8],
+    /// Number of bytes consumed so far
+    position: usize,
+    state: usize,
+    /// Index into the current state's outputs still to be returned
+    pending: usize,
+}
+
+impl Iterator for OverlappingMatches<'_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some(pattern) = self.matcher.outputs[self.state].get(self.pending) {
+                self.pending += 1;
+                return Some(Match {
+                    pattern: *pattern,
+                    start: self.position - self.matcher.pattern_lens[*pattern],
+                    end: self.position,
+                });
+            }
+            let byte = *self.haystack.get(self.position)?;
+            self.state = self.matcher.transitions[self.state * 256 + byte as usize] as usize;
+            self.position += 1;
+            self.pending = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(matcher: &AhoCorasick, haystack: &str) -> Vec<(usize, usize)> {
+        matcher
+            .find_overlapping(haystack.as_bytes())
+            .map(|x| (x.pattern, x.start))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_overlapping() {
+        let matcher = AhoCorasick::new(&["he", "she", "his", "hers"]);
+        assert_eq!(vec![(1, 1), (0, 2), (3, 2)], found(&matcher, "ushers"));
+        assert_eq!(vec![(2, 0)], found(&matcher, "his"));
+        assert!(found(&matcher, "xyz").is_empty());
+        assert!(found(&matcher, "").is_empty());
+    }
+
+    #[test]
+    fn test_digit_word_overlaps() {
+        let matcher = AhoCorasick::new(&["one", "two", "eight", "nine"]);
+        assert_eq!(vec![(2, 0), (1, 4)], found(&matcher, "eightwo"));
+        assert_eq!(vec![(0, 0), (2, 2)], found(&matcher, "oneight"));
+        assert_eq!(vec![(3, 0), (2, 3)], found(&matcher, "nineight"));
+        assert_eq!(vec![(0, 3)], found(&matcher, "nonone"));
+    }
+
+    #[test]
+    fn test_nested_patterns() {
+        let matcher = AhoCorasick::new(&["a", "aa", "aaa"]);
+        let matches: Vec<Match> = matcher.find_overlapping(b"aaa").collect();
+        assert_eq!(6, matches.len());
+        assert_eq!(
+            Match {
+                pattern: 2,
+                start: 0,
+                end: 3
+            },
+            matches[3]
+        );
+    }
+
+    #[test]
+    fn test_empty_and_duplicate_patterns() {
+        let matcher = AhoCorasick::new(&["", "ab", "ab"]);
+        assert_eq!(vec![(1, 1), (2, 1)], found(&matcher, "xab"));
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let matcher = AhoCorasick::new(&["é", "eé"]);
+        let haystack = "café eé";
+        let matches: Vec<Match> = matcher.find_overlapping(haystack.as_bytes()).collect();
+        assert_eq!(3, matches.len());
+        assert_eq!("é", &haystack[matches[0].start..matches[0].end]);
+        assert_eq!("eé", &haystack[matches[1].start..matches[1].end]);
+    }
+}
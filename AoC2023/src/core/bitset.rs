@@ -0,0 +1,189 @@
+/// A set of small unsigned integers stored one bit per value. Holds values below
+/// `BitSet::<WORDS>::CAPACITY`, which is `64 * WORDS`, and never allocates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitSet<const WORDS: usize> {
+    words: [u64; WORDS],
+}
+
+impl<const WORDS: usize> Default for BitSet<WORDS> {
+    fn default() -> BitSet<WORDS> {
+        BitSet { words: [0; WORDS] }
+    }
+}
+
+impl<const WORDS: usize> BitSet<WORDS> {
+    pub const CAPACITY: usize = 64 * WORDS;
+
+    pub fn new() -> BitSet<WORDS> {
+        BitSet::default()
+    }
+
+    /// Builds a set from the values, None if any of them is not below the capacity
+    pub fn from_values(values: impl IntoIterator<Item = usize>) -> Option<BitSet<WORDS>> {
+        let mut set = BitSet::new();
+        for value in values {
+            if value >= Self::CAPACITY {
+                return None;
+            }
+            set.insert(value);
+        }
+        Some(set)
+    }
+
+    /// Adds the value and returns true if it was not already present.
+    /// Panics if the value is not below the capacity.
+    pub fn insert(&mut self, value: usize) -> bool {
+        assert!(
+            value < Self::CAPACITY,
+            "{} does not fit into a set of {} bits",
+            value,
+            Self::CAPACITY
+        );
+        let (word, bit) = (value / 64, 1u64 << (value % 64));
+        let added = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        added
+    }
+
+    /// Removes the value and returns true if it was present
+    pub fn remove(&mut self, value: usize) -> bool {
+        if value >= Self::CAPACITY {
+            return false;
+        }
+        let (word, bit) = (value / 64, 1u64 << (value % 64));
+        let removed = self.words[word] & bit != 0;
+        self.words[word] &= !bit;
+        removed
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        value < Self::CAPACITY && self.words[value / 64] & (1u64 << (value % 64)) != 0
+    }
+
+    /// Number of values in the set
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|x| *x == 0)
+    }
+
+    pub fn union(&self, other: &BitSet<WORDS>) -> BitSet<WORDS> {
+        BitSet {
+            words: std::array::from_fn(|idx| self.words[idx] | other.words[idx]),
+        }
+    }
+
+    pub fn intersection(&self, other: &BitSet<WORDS>) -> BitSet<WORDS> {
+        BitSet {
+            words: std::array::from_fn(|idx| self.words[idx] & other.words[idx]),
+        }
+    }
+
+    /// Size of the intersection without building it
+    pub fn intersection_len(&self, other: &BitSet<WORDS>) -> usize {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// The values in ascending order
+    pub fn iter(&self) -> Iter<'_, WORDS> {
+        Iter {
+            words: &self.words,
+            word: 0,
+            remaining: self.words.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+/// Iterator returned by `BitSet::iter`
+#[derive(Debug, Clone)]
+pub struct Iter<'a, const WORDS: usize> {
+    words: &'a [u64; WORDS],
+    word: usize,
+    /// Bits of the current word not returned yet
+    remaining: u64,
+}
+
+impl<const WORDS: usize> Iterator for Iter<'_, WORDS> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.remaining == 0 {
+            self.word += 1;
+            self.remaining = *self.words.get(self.word)?;
+        }
+        let bit = self.remaining.trailing_zeros() as usize;
+        // Clear the lowest set bit
+        self.remaining &= self.remaining - 1;
+        Some(self.word * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut set: BitSet<2> = BitSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert(0));
+        assert!(set.insert(63));
+        assert!(set.insert(64));
+        assert!(set.insert(127));
+        assert!(!set.insert(64));
+        assert_eq!(4, set.len());
+        assert!(set.contains(63));
+        assert!(!set.contains(62));
+        assert!(!set.contains(1_000));
+        assert!(set.remove(63));
+        assert!(!set.remove(63));
+        assert!(!set.remove(1_000));
+        assert_eq!(vec![0, 64, 127], set.iter().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_range() {
+        let mut set: BitSet<1> = BitSet::new();
+        set.insert(64);
+    }
+
+    #[test]
+    fn test_from_values() {
+        assert_eq!(128, BitSet::<2>::CAPACITY);
+        let set = BitSet::<2>::from_values([5, 3, 100, 3]).unwrap();
+        assert_eq!(vec![3, 5, 100], set.iter().collect::<Vec<usize>>());
+        assert_eq!(None, BitSet::<2>::from_values([5, 128]));
+        assert!(BitSet::<2>::from_values([]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = BitSet::<3>::from_values([1, 2, 70, 150, 191]).unwrap();
+        let b = BitSet::<3>::from_values([2, 3, 70, 191]).unwrap();
+        assert_eq!(
+            vec![2, 70, 191],
+            a.intersection(&b).iter().collect::<Vec<usize>>()
+        );
+        assert_eq!(3, a.intersection_len(&b));
+        assert_eq!(
+            vec![1, 2, 3, 70, 150, 191],
+            a.union(&b).iter().collect::<Vec<usize>>()
+        );
+        assert_eq!(0, a.intersection_len(&BitSet::new()));
+    }
+
+    #[test]
+    fn test_zero_width() {
+        let set: BitSet<0> = BitSet::new();
+        assert_eq!(0, BitSet::<0>::CAPACITY);
+        assert_eq!(None, set.iter().next());
+        assert_eq!(None, BitSet::<0>::from_values([0]));
+    }
+}
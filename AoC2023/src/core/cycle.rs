@@ -0,0 +1,280 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Describes a sequence `x0, f(x0), f(f(x0)), ...` that repeats.
+/// The first `prefix_len` states are never seen again, after that every `cycle_len` states repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix_len: usize,
+    pub cycle_len: usize,
+}
+
+impl Cycle {
+    /// Returns the earliest step that has the same state as step `n`
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.prefix_len {
+            return n;
+        }
+        self.prefix_len + (n - self.prefix_len) % self.cycle_len
+    }
+}
+
+/// Floyd's tortoise and hare. Only keeps two states in memory, so it suits large states,
+/// but calls the transition roughly three times per step.
+pub fn floyd<S, F>(start: S, mut f: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    // Find a meeting point inside the cycle
+    let mut tortoise = f(&start);
+    let mut hare = f(&tortoise);
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        let half_step = f(&hare);
+        hare = f(&half_step);
+    }
+
+    // The distance from the start to the cycle equals the distance from the meeting point
+    let mut prefix_len = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        prefix_len += 1;
+    }
+
+    let mut cycle_len = 1;
+    hare = f(&tortoise);
+    while tortoise != hare {
+        hare = f(&hare);
+        cycle_len += 1;
+    }
+
+    Cycle {
+        prefix_len,
+        cycle_len,
+    }
+}
+
+/// Brent's algorithm. Same memory use as Floyd but fewer calls to the transition.
+pub fn brent<S, F>(start: S, mut f: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    // Search successive powers of two for the cycle length
+    let mut power = 1;
+    let mut cycle_len = 1;
+    let mut tortoise = start.clone();
+    let mut hare = f(&start);
+    while tortoise != hare {
+        if power == cycle_len {
+            tortoise = hare.clone();
+            power *= 2;
+            cycle_len = 0;
+        }
+        hare = f(&hare);
+        cycle_len += 1;
+    }
+
+    // Walk two states that are cycle_len apart until they meet at the start of the cycle
+    let mut prefix_len = 0;
+    tortoise = start.clone();
+    hare = start;
+    for _ in 0..cycle_len {
+        hare = f(&hare);
+    }
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        prefix_len += 1;
+    }
+
+    Cycle {
+        prefix_len,
+        cycle_len,
+    }
+}
+
+/// Every state visited up to the first repeat, as recorded by `detect`.
+#[derive(Debug, Clone)]
+pub struct History<S> {
+    states: Vec<S>,
+    cycle: Cycle,
+}
+
+impl<S> History<S> {
+    pub fn cycle(&self) -> Cycle {
+        self.cycle
+    }
+
+    /// Returns the state after `n` steps without running the transition again
+    pub fn state_at(&self, n: usize) -> &S {
+        &self.states[self.cycle.equivalent_step(n)]
+    }
+}
+
+/// Records every state in a HashMap until one repeats. Calls the transition exactly once
+/// per distinct state and keeps them all, so any later step can be looked up directly.
+pub fn detect<S, F>(start: S, mut f: F) -> History<S>
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> S,
+{
+    let mut seen: HashMap<S, usize> = HashMap::new();
+    let mut states: Vec<S> = Vec::new();
+    let mut state = start;
+    loop {
+        if let Some(first) = seen.get(&state) {
+            let cycle = Cycle {
+                prefix_len: *first,
+                cycle_len: states.len() - first,
+            };
+            return History { states, cycle };
+        }
+        let next = f(&state);
+        seen.insert(state.clone(), states.len());
+        states.push(state);
+        state = next;
+    }
+}
+
+/// Returns the state after `n` steps, skipping the repeated laps of the cycle.
+pub fn fast_forward<S, F>(start: S, mut f: F, n: usize) -> S
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let cycle = brent(start.clone(), &mut f);
+    let mut state = start;
+    for _ in 0..cycle.equivalent_step(n) {
+        state = f(&state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3 -> 8 -> 4 -> 5 -> 6 -> 7 -> 4 ... prefix of 2 and cycle of 4
+    fn step(x: &u32) -> u32 {
+        match x {
+            3 => 8,
+            8 => 4,
+            7 => 4,
+            _ => x + 1,
+        }
+    }
+
+    #[test]
+    fn test_equivalent_step() {
+        let cycle = Cycle {
+            prefix_len: 2,
+            cycle_len: 4,
+        };
+        assert_eq!(0, cycle.equivalent_step(0));
+        assert_eq!(1, cycle.equivalent_step(1));
+        assert_eq!(2, cycle.equivalent_step(2));
+        assert_eq!(5, cycle.equivalent_step(5));
+        assert_eq!(2, cycle.equivalent_step(6));
+        assert_eq!(3, cycle.equivalent_step(1_000_000_003));
+    }
+
+    #[test]
+    fn test_floyd() {
+        let expected = Cycle {
+            prefix_len: 2,
+            cycle_len: 4,
+        };
+        assert_eq!(expected, floyd(3, step));
+        // Starting inside the cycle
+        let expected = Cycle {
+            prefix_len: 0,
+            cycle_len: 4,
+        };
+        assert_eq!(expected, floyd(5, step));
+    }
+
+    #[test]
+    fn test_brent() {
+        let expected = Cycle {
+            prefix_len: 2,
+            cycle_len: 4,
+        };
+        assert_eq!(expected, brent(3, step));
+        let expected = Cycle {
+            prefix_len: 0,
+            cycle_len: 4,
+        };
+        assert_eq!(expected, brent(5, step));
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let expected = Cycle {
+            prefix_len: 3,
+            cycle_len: 1,
+        };
+        let f = |x: &u32| if *x < 3 { x + 1 } else { *x };
+        assert_eq!(expected, floyd(0, f));
+        assert_eq!(expected, brent(0, f));
+        assert_eq!(expected, detect(0, f).cycle());
+    }
+
+    #[test]
+    fn test_detect() {
+        let history = detect(3, step);
+        let expected = Cycle {
+            prefix_len: 2,
+            cycle_len: 4,
+        };
+        assert_eq!(expected, history.cycle());
+        assert_eq!(3, *history.state_at(0));
+        assert_eq!(4, *history.state_at(2));
+        assert_eq!(7, *history.state_at(5));
+        assert_eq!(4, *history.state_at(6));
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        // A pseudo random walk over 0..1000
+        let f = |x: &u64| (x * x + 7) % 1000;
+        for start in [0u64, 1, 42, 999] {
+            let expected = detect(start, f).cycle();
+            assert_eq!(expected, floyd(start, f));
+            assert_eq!(expected, brent(start, f));
+        }
+    }
+
+    #[test]
+    fn test_fast_forward_grid() {
+        // Rotates a small grid a quarter turn each step, with a one-off change on the first step
+        let start: Vec<Vec<char>> = vec!["#..".chars().collect(), ".#.".chars().collect()];
+        let rotate = |grid: &Vec<Vec<char>>| {
+            let rows = grid.len();
+            let cols = grid[0].len();
+            let mut rotated = vec![vec!['.'; rows]; cols];
+            for (r, line) in grid.iter().enumerate() {
+                for (c, x) in line.iter().enumerate() {
+                    rotated[c][rows - 1 - r] = if *x == '#' { 'O' } else { *x };
+                }
+            }
+            rotated
+        };
+
+        let cycle = brent(start.clone(), rotate);
+        assert_eq!(
+            Cycle {
+                prefix_len: 1,
+                cycle_len: 4
+            },
+            cycle
+        );
+
+        let n = 1_000_000_001;
+        let expected = detect(start.clone(), rotate).state_at(n).clone();
+        assert_eq!(expected, fast_forward(start.clone(), rotate, n));
+        assert_eq!(rotate(&start), expected);
+    }
+}
@@ -1,31 +1,210 @@
 extern crate unicode_segmentation;
+use std::{collections::HashMap, fmt, io::BufRead, str::FromStr};
+
 use unicode_segmentation::UnicodeSegmentation;
 
-/// Receives input and prints output
-pub fn day01_part1(lines: &mut dyn Iterator<Item = String>) {
-    let total: i32 = lines.map(|x| day01_part1_line_handler(x.as_str())).sum();
-    println!("Sum {}", total);
+use super::aho_corasick::{AhoCorasick, Match};
+use crate::io::options;
+
+/// Receives input and prints output.
+/// Scans raw bytes by default, `--graphemes` uses the Unicode aware line handler instead.
+/// Lines without a digit are skipped, or reported as errors with `--strict`.
+pub fn day01_part1(reader: &mut dyn BufRead, options: &[String]) -> Result<(), String> {
+    let mode = Mode::from_options(options);
+    if options::has_flag(options, "--graphemes") {
+        let mut lines = BufRead::lines(reader).map_while(Result::ok);
+        print_calibration(sum_calibration(&mut lines, mode, |x| {
+            Ok(day01_part1_line_handler(x).map(i64::from))
+        }))
+    } else {
+        print_calibration(sum_calibration_bytes(reader, mode))
+    }
+}
+
+/// Receives input and prints output.
+/// `--vocab FILE` or `--words one=1,two=2` replaces the default digit words.
+/// Lines without a digit are skipped, or reported as errors with `--strict`.
+pub fn day01_part2(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let mode = Mode::from_options(options);
+    let vocabulary = DigitVocabulary::from_options(options).map_err(|x| x.to_string())?;
+    print_calibration(sum_calibration(lines, mode, |x| {
+        day01_part2_line_handler(x, &vocabulary)
+    }))
+}
+
+/// What to do with lines that have no digit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Skip them and count them in the summary
+    Lenient,
+    /// Fail, listing every offending line
+    Strict,
+}
+
+impl Mode {
+    fn from_options(options: &[String]) -> Mode {
+        if options::has_flag(options, "--strict") {
+            Mode::Strict
+        } else {
+            Mode::Lenient
+        }
+    }
+}
+
+/// Total of the calibration values, with the 1-based numbers of any skipped lines
+#[derive(Debug, PartialEq, Eq)]
+struct Calibration {
+    total: i64,
+    skipped: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CalibrationError {
+    message: String,
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Sums the value of every line, `handler` returns None for a line without a digit
+fn sum_calibration<F>(
+    lines: &mut dyn Iterator<Item = String>,
+    mode: Mode,
+    handler: F,
+) -> Result<Calibration, CalibrationError>
+where
+    F: Fn(&str) -> Result<Option<i64>, CalibrationError>,
+{
+    let mut calibration = Calibration {
+        total: 0,
+        skipped: Vec::new(),
+    };
+    for (idx, line) in lines.enumerate() {
+        let value = handler(line.as_str()).map_err(|x| CalibrationError {
+            message: format!("line {}: {}", idx + 1, x),
+        })?;
+        match value {
+            Some(value) => {
+                calibration.total =
+                    calibration
+                        .total
+                        .checked_add(value)
+                        .ok_or_else(|| CalibrationError {
+                            message: format!("the total overflows an i64 at line {}", idx + 1),
+                        })?
+            }
+            None => calibration.skipped.push(idx + 1),
+        }
+    }
+    apply_mode(calibration, mode)
+}
+
+/// Part 1 over raw bytes. One buffer is reused for every line and each line is scanned
+/// from both ends, so nothing is allocated per line. An ASCII digit byte is never part of
+/// a multi-byte UTF-8 sequence, so the result matches the grapheme path on valid input.
+fn sum_calibration_bytes(
+    reader: &mut dyn BufRead,
+    mode: Mode,
+) -> Result<Calibration, CalibrationError> {
+    let mut calibration = Calibration {
+        total: 0,
+        skipped: Vec::new(),
+    };
+    let mut buffer: Vec<u8> = Vec::with_capacity(256);
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|x| CalibrationError {
+                message: format!("could not read line {}: {}", line_number + 1, x),
+            })?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        match line_value_bytes(&buffer) {
+            Some(value) => calibration.total += value as i64,
+            None => calibration.skipped.push(line_number),
+        }
+    }
+    apply_mode(calibration, mode)
+}
+
+/// Fails in strict mode when any line was skipped
+fn apply_mode(calibration: Calibration, mode: Mode) -> Result<Calibration, CalibrationError> {
+    if mode == Mode::Strict && !calibration.skipped.is_empty() {
+        return Err(CalibrationError {
+            message: format!(
+                "no digit found on line(s) {}",
+                join_line_numbers(&calibration.skipped)
+            ),
+        });
+    }
+    Ok(calibration)
+}
+
+/// First and last ASCII digit of the line as a two digit number, None when there is no digit
+fn line_value_bytes(line: &[u8]) -> Option<i32> {
+    let first = line.iter().find(|x| x.is_ascii_digit())?;
+    let last = line.iter().rev().find(|x| x.is_ascii_digit())?;
+    Some(((first - b'0') * 10 + (last - b'0')) as i32)
 }
 
-/// Receives input and prints output
-pub fn day01_part2(lines: &mut dyn Iterator<Item = String>) {
-    let total: i32 = lines.map(|x| day01_part2_line_handler(x.as_str())).sum();
-    println!("Sum {}", total);
+fn join_line_numbers(line_numbers: &[usize]) -> String {
+    line_numbers
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Prints the total and any skipped lines, or passes the error on for `main` to report
+fn print_calibration(calibration: Result<Calibration, CalibrationError>) -> Result<(), String> {
+    let calibration = calibration.map_err(|x| x.to_string())?;
+    println!("Sum {}", calibration.total);
+    if !calibration.skipped.is_empty() {
+        println!(
+            "Skipped {} line(s) without a digit: {}",
+            calibration.skipped.len(),
+            join_line_numbers(&calibration.skipped)
+        );
+    }
+    Ok(())
 }
 
-/// Processes a string according to the rules for Day 1 Part 1
-fn day01_part1_line_handler(input: &str) -> i32 {
-    let first_digit_char = get_first_digit_char(input);
-    let last_digit_char = get_first_digit_char(reverse_graphemes(input).as_str());
-    format_char_digits_to_int(first_digit_char, last_digit_char)
+/// Processes a string according to the rules for Day 1 Part 1.
+/// None when the line has no digit.
+fn day01_part1_line_handler(input: &str) -> Option<i32> {
+    let first_digit_char = get_first_digit_char(input)?;
+    let last_digit_char = get_first_digit_char(reverse_graphemes(input).as_str())?;
+    Some(format_char_digits_to_int(first_digit_char, last_digit_char))
 }
 
-/// Processes a string according to the rules for Day 1 Part 2
-fn day01_part2_line_handler(input: &str) -> i32 {
-    let indices_and_digits = get_index_and_digit(input);
-    let first_digit_char = word_to_digit_char(indices_and_digits.1);
-    let last_digit_char = word_to_digit_char(indices_and_digits.3);
-    format_char_digits_to_int(first_digit_char, last_digit_char)
+/// Processes a string according to the rules for Day 1 Part 2.
+/// None when the line has no digit or digit word.
+fn day01_part2_line_handler(
+    input: &str,
+    vocabulary: &DigitVocabulary,
+) -> Result<Option<i64>, CalibrationError> {
+    let Some(indices_and_digits) = get_index_and_digit(input, vocabulary) else {
+        return Ok(None);
+    };
+    let first_value = vocabulary.value(indices_and_digits.1);
+    let last_value = vocabulary.value(indices_and_digits.3);
+    let value = concatenate_values(first_value, last_value).ok_or_else(|| CalibrationError {
+        message: format!(
+            "'{}' followed by '{}' does not fit in an i64",
+            first_value, last_value
+        ),
+    })?;
+    Ok(Some(value))
 }
 
 /// Reverse the graphemes of the input and returns a new String
@@ -40,94 +219,605 @@ fn format_char_digits_to_int(a: char, b: char) -> i32 {
     number_str.parse::<i32>().unwrap_or_default()
 }
 
+/// Writes the decimal form of `b` after that of `a`, so 12 and 345 give 12345.
+/// None when the result does not fit in an i64.
+fn concatenate_values(a: u32, b: u32) -> Option<i64> {
+    let shift = 10i64.checked_pow(b.checked_ilog10().unwrap_or(0) + 1)?;
+    (a as i64).checked_mul(shift)?.checked_add(b as i64)
+}
+
 /// Returns the first ASCII digit as a char from the string
-fn get_first_digit_char(input: &str) -> char {
-    let digit_opt = input.chars().find(char::is_ascii_digit);
-    digit_opt.unwrap_or('\0')
-}
-
-/// When a string represents a word or a number return it as a digit
-fn word_to_digit_char(input: &str) -> char {
-    match input.to_lowercase().as_str() {
-        "0" | "zero" => '0',
-        "1" | "one" => '1',
-        "2" | "two" => '2',
-        "3" | "three" => '3',
-        "4" | "four" => '4',
-        "5" | "five" => '5',
-        "6" | "six" => '6',
-        "7" | "seven" => '7',
-        "8" | "eight" => '8',
-        "9" | "nine" => '9',
-        _ => panic!("'{}' was not an expected digit word", input),
-    }
-}
-
-const DIGITS: [&str; 20] = [
-    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "zero", "one", "two", "three", "four",
-    "five", "six", "seven", "eight", "nine",
+fn get_first_digit_char(input: &str) -> Option<char> {
+    input.chars().find(char::is_ascii_digit)
+}
+
+/// Largest value a vocabulary word may stand for, so that a line's value and the sum over
+/// many lines stay far from the limits of an i64
+const MAX_WORD_VALUE: u32 = 999_999;
+
+const DIGITS: [(&str, u32); 20] = [
+    ("0", 0),
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+    ("zero", 0),
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
 ];
 
-fn get_index_and_digit(input: &str) -> (usize, &str, usize, &str) {
-    // locates the indices of every digit and word
-    let indices: Vec<(usize, &str)> = DIGITS
-        .iter()
-        .flat_map(|x| input.match_indices(*x).collect::<Vec<(usize, &str)>>())
-        .collect();
+/// The words that count as digits and the value each one stands for
+#[derive(Debug, Clone)]
+struct DigitVocabulary {
+    values: HashMap<String, u32>,
+    matcher: AhoCorasick,
+}
 
-    // reduces down to the first index and word
-    let min_index = indices
-        .iter()
-        .reduce(|a, b| if a.0 < b.0 { a } else { b })
-        .expect("did not find an digit in the string");
-    let max_index = indices
-        .iter()
-        .reduce(|a, b| if a.0 < b.0 { b } else { a })
-        .expect("did not find an digit in the string");
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct VocabularyError {
+    message: String,
+}
+
+impl fmt::Display for VocabularyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Default for DigitVocabulary {
+    /// ASCII digits and the English words for them
+    fn default() -> DigitVocabulary {
+        DigitVocabulary::new(
+            DIGITS
+                .iter()
+                .map(|(word, value)| (word.to_string(), *value)),
+        )
+        .expect("the default vocabulary is valid")
+    }
+}
 
-    (min_index.0, min_index.1, max_index.0, max_index.1)
+impl DigitVocabulary {
+    /// Builds the vocabulary and its matcher, rejecting empty or repeated words and values
+    /// above MAX_WORD_VALUE
+    fn new(
+        entries: impl IntoIterator<Item = (String, u32)>,
+    ) -> Result<DigitVocabulary, VocabularyError> {
+        let mut values: HashMap<String, u32> = HashMap::new();
+        let mut words: Vec<String> = Vec::new();
+        for (word, value) in entries {
+            if word.is_empty() {
+                return Err(VocabularyError {
+                    message: format!("empty word for value {}", value),
+                });
+            }
+            if value > MAX_WORD_VALUE {
+                return Err(VocabularyError {
+                    message: format!(
+                        "'{}' stands for {}, more than the largest value {}",
+                        word, value, MAX_WORD_VALUE
+                    ),
+                });
+            }
+            if values.insert(word.clone(), value).is_some() {
+                return Err(VocabularyError {
+                    message: format!("'{}' appears more than once", word),
+                });
+            }
+            words.push(word);
+        }
+        if words.is_empty() {
+            return Err(VocabularyError {
+                message: "the vocabulary has no words".to_string(),
+            });
+        }
+        Ok(DigitVocabulary {
+            matcher: AhoCorasick::new(&words),
+            values,
+        })
+    }
+
+    /// Reads the vocabulary from `--vocab FILE` or `--words ...`, the default otherwise
+    fn from_options(options: &[String]) -> Result<DigitVocabulary, VocabularyError> {
+        let spec = options::read_spec(options, "--words", "--vocab")
+            .map_err(|x| VocabularyError { message: x.message })?;
+        match spec {
+            Some(spec) => DigitVocabulary::from_str(&spec),
+            None => Ok(DigitVocabulary::default()),
+        }
+    }
+
+    /// Value of a word found by the matcher
+    fn value(&self, word: &str) -> u32 {
+        *self
+            .values
+            .get(word)
+            .unwrap_or_else(|| panic!("'{}' was not an expected digit word", word))
+    }
+}
+
+impl FromStr for DigitVocabulary {
+    type Err = VocabularyError;
+
+    /// Parses `word=value` entries with `options::parse_key_values`
+    fn from_str(input: &str) -> Result<DigitVocabulary, Self::Err> {
+        let entries = options::parse_key_values(input, "word=value")
+            .map_err(|x| VocabularyError { message: x.message })?
+            .into_iter()
+            .map(|(word, value)| {
+                let value = value.parse::<u32>().map_err(|x| VocabularyError {
+                    message: format!("'{}={}' has an invalid value: {}", word, value, x),
+                })?;
+                Ok((word.to_string(), value))
+            })
+            .collect::<Result<Vec<(String, u32)>, VocabularyError>>()?;
+        DigitVocabulary::new(entries)
+    }
+}
+
+/// Finds the first and last digit or digit word in a single pass over the input.
+/// Overlapping words like 'eightwo' count as both 'eight' and 'two'. When words start at the
+/// same index, like 'six' and 'sixteen', the longest one counts.
+fn get_index_and_digit<'a>(
+    input: &'a str,
+    vocabulary: &DigitVocabulary,
+) -> Option<(usize, &'a str, usize, &'a str)> {
+    // locates the indices of every digit and word, and reduces down to the first and last
+    let mut matches = vocabulary.matcher.find_overlapping(input.as_bytes());
+    let first = matches.next()?;
+    let longer = |x: &Match, y: &Match| x.end - x.start > y.end - y.start;
+    let (min_index, max_index) = matches.fold((first, first), |(min, max), x| {
+        let first = x.start < min.start || (x.start == min.start && longer(&x, &min));
+        let last = x.start > max.start || (x.start == max.start && longer(&x, &max));
+        (if first { x } else { min }, if last { x } else { max })
+    });
+
+    Some((
+        min_index.start,
+        &input[min_index.start..min_index.end],
+        max_index.start,
+        &input[max_index.start..max_index.end],
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+
     use super::*;
+    use crate::core::xorshift::XorShift;
 
     #[test]
     fn test_day01_part1_line_handler() {
-        assert_eq!(12, day01_part1_line_handler("1abc2"));
-        assert_eq!(38, day01_part1_line_handler("pqr3stu8vwx"));
-        assert_eq!(15, day01_part1_line_handler("a1b2c3d4e5f"));
-        assert_eq!(77, day01_part1_line_handler("treb7uchet"));
+        assert_eq!(Some(12), day01_part1_line_handler("1abc2"));
+        assert_eq!(Some(38), day01_part1_line_handler("pqr3stu8vwx"));
+        assert_eq!(Some(15), day01_part1_line_handler("a1b2c3d4e5f"));
+        assert_eq!(Some(77), day01_part1_line_handler("treb7uchet"));
     }
 
     #[test]
     fn test_day01_part2() {
-        assert_eq!(29, day01_part2_line_handler("two1nine"));
-        assert_eq!(83, day01_part2_line_handler("eightwothree"));
-        assert_eq!(13, day01_part2_line_handler("abcone2threexyz"));
-        assert_eq!(24, day01_part2_line_handler("xtwone3four"));
-        assert_eq!(42, day01_part2_line_handler("4nineeightseven2"));
-        assert_eq!(14, day01_part2_line_handler("zoneight234"));
-        assert_eq!(76, day01_part2_line_handler("7pqrstsixteen"));
+        let vocabulary = DigitVocabulary::default();
+        assert_eq!(
+            Ok(Some(29)),
+            day01_part2_line_handler("two1nine", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(83)),
+            day01_part2_line_handler("eightwothree", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(13)),
+            day01_part2_line_handler("abcone2threexyz", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(24)),
+            day01_part2_line_handler("xtwone3four", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(42)),
+            day01_part2_line_handler("4nineeightseven2", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(14)),
+            day01_part2_line_handler("zoneight234", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(76)),
+            day01_part2_line_handler("7pqrstsixteen", &vocabulary)
+        );
+        // Overlapping words at either end
+        assert_eq!(
+            Ok(Some(82)),
+            day01_part2_line_handler("eightwo", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(18)),
+            day01_part2_line_handler("oneight", &vocabulary)
+        );
+        assert_eq!(Ok(Some(11)), day01_part2_line_handler("one", &vocabulary));
+    }
+
+    /// Part 1 line handler in the shape `sum_calibration` expects
+    fn part1_handler(input: &str) -> Result<Option<i64>, CalibrationError> {
+        Ok(day01_part1_line_handler(input).map(i64::from))
+    }
+
+    fn input_lines(input: &str) -> impl Iterator<Item = String> + '_ {
+        input.split('\n').map(|x| x.to_string())
+    }
+
+    #[test]
+    fn test_lines_without_digits() {
+        let vocabulary = DigitVocabulary::default();
+        assert_eq!(None, day01_part1_line_handler("pqrstuvwx"));
+        assert_eq!(None, day01_part1_line_handler(""));
+        assert_eq!(Ok(None), day01_part2_line_handler("pqrstuvwx", &vocabulary));
+        assert_eq!(Ok(None), day01_part2_line_handler("", &vocabulary));
+    }
+
+    #[test]
+    fn test_sum_calibration_lenient() {
+        let input = "1abc2\nnothing\ntreb7uchet\n";
+        let calibration = sum_calibration(&mut input_lines(input), Mode::Lenient, part1_handler);
+        assert_eq!(
+            Ok(Calibration {
+                total: 89,
+                skipped: vec![2, 4]
+            }),
+            calibration
+        );
+
+        let vocabulary = DigitVocabulary::default();
+        let calibration = sum_calibration(&mut input_lines(input), Mode::Lenient, |x| {
+            day01_part2_line_handler(x, &vocabulary)
+        });
+        assert_eq!(
+            Ok(Calibration {
+                total: 89,
+                skipped: vec![2, 4]
+            }),
+            calibration
+        );
+    }
+
+    #[test]
+    fn test_sum_calibration_strict() {
+        let input = "1abc2\nnothing\ntreb7uchet\nnone here";
+        let calibration = sum_calibration(&mut input_lines(input), Mode::Strict, part1_handler);
+        assert_eq!(
+            "no digit found on line(s) 2, 4",
+            calibration.unwrap_err().to_string()
+        );
+
+        // 'none here' contains 'one' so only line 2 fails in part 2
+        let vocabulary = DigitVocabulary::default();
+        let calibration = sum_calibration(&mut input_lines(input), Mode::Strict, |x| {
+            day01_part2_line_handler(x, &vocabulary)
+        });
+        assert_eq!(
+            "no digit found on line(s) 2",
+            calibration.unwrap_err().to_string()
+        );
+
+        let calibration = sum_calibration(
+            &mut input_lines("1abc2\ntreb7uchet"),
+            Mode::Strict,
+            part1_handler,
+        );
+        assert_eq!(
+            Ok(Calibration {
+                total: 89,
+                skipped: vec![]
+            }),
+            calibration
+        );
+    }
+
+    #[test]
+    fn test_line_value_bytes() {
+        assert_eq!(Some(12), line_value_bytes(b"1abc2\n"));
+        assert_eq!(Some(77), line_value_bytes(b"treb7uchet\r\n"));
+        assert_eq!(Some(15), line_value_bytes(b"a1b2c3d4e5f"));
+        assert_eq!(None, line_value_bytes(b"pqrstuvwx\n"));
+        assert_eq!(None, line_value_bytes(b""));
+    }
+
+    #[test]
+    fn test_sum_calibration_bytes() {
+        let input = "1abc2\r\nnothing\ntreb7uchet\n\n9";
+        let mut reader = Cursor::new(input.as_bytes());
+        assert_eq!(
+            Ok(Calibration {
+                total: 188,
+                skipped: vec![2, 4]
+            }),
+            sum_calibration_bytes(&mut reader, Mode::Lenient)
+        );
+        let mut reader = Cursor::new(input.as_bytes());
+        assert_eq!(
+            "no digit found on line(s) 2, 4",
+            sum_calibration_bytes(&mut reader, Mode::Strict)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_bytes_agree_with_graphemes() {
+        // Combining marks, emoji and multi-byte letters around the digits
+        let input =
+            "é1\u{20E3}ü2\n日本3語\n4\u{1F600}x5\u{301}\nkeine Ziffern\n\u{1F1EF}\u{1F1F5}7";
+        let mut lines = input_lines(input);
+        let expected = sum_calibration(&mut lines, Mode::Lenient, part1_handler);
+        let mut reader = Cursor::new(input.as_bytes());
+        assert_eq!(expected, sum_calibration_bytes(&mut reader, Mode::Lenient));
+        assert_eq!(12 + 33 + 45 + 77, expected.unwrap().total);
+
+        let generated = synthetic_lines(2_000).join("\n");
+        let mut lines = input_lines(&generated);
+        let mut reader = Cursor::new(generated.as_bytes());
+        assert_eq!(
+            sum_calibration(&mut lines, Mode::Lenient, part1_handler),
+            sum_calibration_bytes(&mut reader, Mode::Lenient)
+        );
+    }
+
+    /// Compares the grapheme path against the byte scanner on a large generated input.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_day01_part1`
+    #[test]
+    #[ignore]
+    fn bench_day01_part1() {
+        let input = synthetic_lines(2_000_000).join("\n");
+
+        let start = std::time::Instant::now();
+        let mut lines = input_lines(&input);
+        let graphemes = sum_calibration(&mut lines, Mode::Lenient, part1_handler);
+        let graphemes_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut reader = Cursor::new(input.as_bytes());
+        let bytes = sum_calibration_bytes(&mut reader, Mode::Lenient);
+        let bytes_time = start.elapsed();
+
+        println!(
+            "{} MB: graphemes {:?}, bytes {:?}",
+            input.len() / 1_000_000,
+            graphemes_time,
+            bytes_time
+        );
+        assert_eq!(graphemes, bytes);
+    }
+
+    #[test]
+    fn test_mode_from_options() {
+        assert_eq!(Mode::Strict, Mode::from_options(&["--strict".to_string()]));
+        assert_eq!(Mode::Lenient, Mode::from_options(&[]));
     }
 
     #[test]
     fn test_get_digit_char() {
-        assert_eq!('1', get_first_digit_char("1abc2"));
-        assert_eq!('3', get_first_digit_char("pqr3stu8vwx"));
-        assert_eq!('\0', get_first_digit_char("pqrstuvwx"));
+        assert_eq!(Some('1'), get_first_digit_char("1abc2"));
+        assert_eq!(Some('3'), get_first_digit_char("pqr3stu8vwx"));
+        assert_eq!(None, get_first_digit_char("pqrstuvwx"));
     }
 
     #[test]
     fn test_get_index_of_digit() {
-        assert_eq!((1, "1", 1, "1"), get_index_and_digit("a1aaaaa"));
-        assert_eq!((2, "two", 6, "one"), get_index_and_digit("abtwoaoneaaaa"));
-        assert_eq!((9, "3", 12, "7"), get_index_and_digit("abtw_othr3aa7bas"));
+        let vocabulary = DigitVocabulary::default();
+        assert_eq!(
+            Some((1, "1", 1, "1")),
+            get_index_and_digit("a1aaaaa", &vocabulary)
+        );
+        assert_eq!(
+            Some((2, "two", 6, "one")),
+            get_index_and_digit("abtwoaoneaaaa", &vocabulary)
+        );
+        assert_eq!(
+            Some((9, "3", 12, "7")),
+            get_index_and_digit("abtw_othr3aa7bas", &vocabulary)
+        );
+        assert_eq!(
+            Some((0, "eight", 4, "two")),
+            get_index_and_digit("eightwo", &vocabulary)
+        );
+    }
+
+    /// The approach used before the matcher: one `match_indices` pass per entry of DIGITS
+    fn match_indices_index_and_digit(input: &str) -> (usize, &str, usize, &str) {
+        let indices: Vec<(usize, &str)> = DIGITS
+            .iter()
+            .flat_map(|x| input.match_indices(x.0).collect::<Vec<(usize, &str)>>())
+            .collect();
+        let min_index = indices.iter().min_by_key(|x| x.0).unwrap();
+        let max_index = indices.iter().max_by_key(|x| x.0).unwrap();
+        (min_index.0, min_index.1, max_index.0, max_index.1)
+    }
+
+    /// Lines of random letters with digits and digit words sprinkled in
+    fn synthetic_lines(count: usize) -> Vec<String> {
+        let mut random = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut next = move |bound: u64| random.below(bound);
+        (0..count)
+            .map(|_| {
+                let mut line = String::new();
+                (0..(8 + next(40))).for_each(|_| match next(10) {
+                    0 => line.push_str(DIGITS[next(20) as usize].0),
+                    _ => line.push((b'a' + next(26) as u8) as char),
+                });
+                line.push_str(DIGITS[next(20) as usize].0);
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matcher_agrees_with_match_indices() {
+        let vocabulary = DigitVocabulary::default();
+        synthetic_lines(2_000).iter().for_each(|line| {
+            assert_eq!(
+                Some(match_indices_index_and_digit(line)),
+                get_index_and_digit(line, &vocabulary),
+                "{}",
+                line
+            );
+        });
+    }
+
+    /// Compares the old `match_indices` scan against the matcher on a large synthetic input.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_day01_part2`
+    #[test]
+    #[ignore]
+    fn bench_day01_part2() {
+        let lines = synthetic_lines(1_000_000);
+        let vocabulary = DigitVocabulary::default();
+
+        let start = std::time::Instant::now();
+        let old_total: i64 = lines
+            .iter()
+            .map(|x| {
+                let (_, first, _, last) = match_indices_index_and_digit(x);
+                format!("{}{}", vocabulary.value(first), vocabulary.value(last))
+                    .parse::<i64>()
+                    .unwrap()
+            })
+            .sum();
+        let old_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let new_total: i64 = lines
+            .iter()
+            .filter_map(|x| day01_part2_line_handler(x, &vocabulary).unwrap())
+            .sum();
+        let new_time = start.elapsed();
+
+        println!(
+            "{} lines: match_indices {:?}, aho-corasick {:?}",
+            lines.len(),
+            old_time,
+            new_time
+        );
+        assert_eq!(old_total, new_total);
+    }
+
+    #[test]
+    fn test_vocabulary_value() {
+        let vocabulary = DigitVocabulary::default();
+        assert_eq!(3, vocabulary.value("three"));
+        assert_eq!(3, vocabulary.value("3"));
+    }
+
+    #[test]
+    fn test_custom_vocabulary() {
+        let vocabulary = DigitVocabulary::from_str(
+            "ten=10, eleven=11, twelve=12\n# French\nun=1\ndeux=2\n\ntrois=3",
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(1012)),
+            day01_part2_line_handler("xtenaeleventwelve", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(32)),
+            day01_part2_line_handler("troisun4deux", &vocabulary)
+        );
+        // Words outside the table, plain digits included, are ignored
+        assert_eq!(
+            Ok(Some(11)),
+            day01_part2_line_handler("one1un", &vocabulary)
+        );
+    }
+
+    #[test]
+    fn test_vocabulary_prefers_longest_word() {
+        let vocabulary =
+            DigitVocabulary::from_str("one=1,six=6,sixteen=16,seven=7,seventeen=17").unwrap();
+        assert_eq!(
+            Ok(Some(1616)),
+            day01_part2_line_handler("sixteen", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(1717)),
+            day01_part2_line_handler("xseventeenx", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(116)),
+            day01_part2_line_handler("onesixteen", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(176)),
+            day01_part2_line_handler("seventeensix", &vocabulary)
+        );
+        assert_eq!(
+            Ok(Some(67)),
+            day01_part2_line_handler("sixseven", &vocabulary)
+        );
+    }
+
+    #[test]
+    fn test_vocabulary_errors() {
+        assert!(DigitVocabulary::from_str("one").is_err());
+        assert!(DigitVocabulary::from_str("one=x").is_err());
+        assert!(DigitVocabulary::from_str("one=-1").is_err());
+        assert!(DigitVocabulary::from_str("=1").is_err());
+        assert!(DigitVocabulary::from_str("").is_err());
+        assert_eq!(
+            "'one' appears more than once",
+            DigitVocabulary::from_str("one=1,one=2")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_concatenate_values() {
+        assert_eq!(Some(12345), concatenate_values(12, 345));
+        assert_eq!(Some(10), concatenate_values(1, 0));
+        assert_eq!(Some(7), concatenate_values(0, 7));
+        assert_eq!(
+            Some(999_999_999_999),
+            concatenate_values(MAX_WORD_VALUE, MAX_WORD_VALUE)
+        );
+        assert_eq!(None, concatenate_values(u32::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn test_large_vocabulary_values() {
+        let vocabulary = DigitVocabulary::from_str("big=99999").unwrap();
+        assert_eq!(
+            Ok(Some(9_999_999_999)),
+            day01_part2_line_handler("xbigx", &vocabulary)
+        );
+        assert_eq!(
+            "'big' stands for 1000000, more than the largest value 999999",
+            DigitVocabulary::from_str("big=1000000")
+                .unwrap_err()
+                .to_string()
+        );
     }
 
     #[test]
-    fn test_word_to_digit() {
-        assert_eq!('3', word_to_digit_char("three"));
-        assert_eq!('3', word_to_digit_char("3"));
+    fn test_vocabulary_from_options() {
+        let options = vec!["--words".to_string(), "a=1,b=2".to_string()];
+        let vocabulary = DigitVocabulary::from_options(&options).unwrap();
+        assert_eq!(Ok(Some(21)), day01_part2_line_handler("xbyaz", &vocabulary));
+        let vocabulary = DigitVocabulary::from_options(&[]).unwrap();
+        assert_eq!(
+            Ok(Some(29)),
+            day01_part2_line_handler("two1nine", &vocabulary)
+        );
+        let options = vec!["--vocab=./does/not/exist.txt".to_string()];
+        assert!(DigitVocabulary::from_options(&options).is_err());
     }
 }
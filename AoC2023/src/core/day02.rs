@@ -1,220 +1,989 @@
-/// Receives input and prints output
-pub fn day02_part1(lines: &mut dyn Iterator<Item = String>) {
-    let total = day02_part1_handler(lines);
+use std::{fmt, rc::Rc, str::FromStr};
+
+use super::json::Json;
+use crate::io::options;
+
+/// Receives input and prints output.
+/// `--bag red=12,green=13,blue=14` or `--bag-file FILE` replaces the puzzle's bag.
+pub fn day02_part1(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let total = bag_spec_from_options(options)
+        .and_then(|x| day02_part1_handler(lines, &x))
+        .map_err(|x| x.to_string())?;
     println!("Sum {}", total);
+    Ok(())
+}
+
+/// Receives input and prints which games are possible with the bag and, for every
+/// impossible game, each pull that breaks a limit. Takes the same bag options as part 1.
+pub fn day02_query(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let report = bag_spec_from_options(options)
+        .and_then(|x| day02_query_handler(lines, &x))
+        .map_err(|x| x.to_string())?;
+    report.iter().for_each(|x| println!("{}", x));
+    Ok(())
 }
 
 /// Receives input and prints output
-pub fn day02_part2(lines: &mut dyn Iterator<Item = String>) {
-    let total = day02_part2_handler(lines);
+pub fn day02_part2(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let total = day02_part2_handler(lines).map_err(|x| x.to_string())?;
     println!("Power total {}", total);
+    Ok(())
+}
+
+/// Receives input and prints summary statistics over the game log, as tables or
+/// as JSON with `--json`
+pub fn day02_stats(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let stats = parse_games(lines)
+        .and_then(|(palette, games)| game_stats(&games, &palette))
+        .map_err(|x| x.to_string())?;
+    if options::has_flag(options, "--json") {
+        println!("{}", stats.to_json());
+    } else {
+        stats.to_table().iter().for_each(|x| println!("{}", x));
+    }
+    Ok(())
+}
+
+/// An interned colour name, clones share the same allocation
+pub type Colour = Rc<str>;
+
+/// Every colour seen so far, in the order they first appeared
+#[derive(Debug, Default)]
+pub struct Palette {
+    colours: Vec<Colour>,
+}
+
+impl Palette {
+    /// Returns the shared name for the colour, adding it when it is new
+    pub fn intern(&mut self, name: &str) -> Colour {
+        match self.get(name) {
+            Some(colour) => colour,
+            None => {
+                let colour: Colour = Rc::from(name);
+                self.colours.push(colour.clone());
+                colour
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Colour> {
+        self.colours.iter().find(|x| x.as_ref() == name).cloned()
+    }
+
+    pub fn colours(&self) -> &[Colour] {
+        &self.colours
+    }
+}
+
+/// Number of cubes of each colour, in the order the colours were listed.
+/// A colour that is not listed has a count of 0.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct CubeCounts {
+    counts: Vec<(Colour, u32)>,
+}
+
+impl CubeCounts {
+    pub fn get(&self, colour: &str) -> u32 {
+        self.counts
+            .iter()
+            .find(|(x, _)| x.as_ref() == colour)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+
+    /// Replaces the count of a colour that is already listed, otherwise appends it
+    pub fn set(&mut self, colour: Colour, count: u32) {
+        match self.counts.iter_mut().find(|(x, _)| *x == colour) {
+            Some(entry) => entry.1 = count,
+            None => self.counts.push((colour, count)),
+        }
+    }
+
+    /// Each colour and its count, in the order they were listed
+    pub fn iter(&self) -> impl Iterator<Item = (&Colour, u32)> {
+        self.counts.iter().map(|(colour, count)| (colour, *count))
+    }
+
+    /// True when every colour in self is available in the bag
+    fn fits_within(&self, bag: &CubeCounts) -> bool {
+        self.counts
+            .iter()
+            .all(|(colour, count)| *count <= bag.get(colour))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CubeError {
+    message: String,
+}
+
+impl fmt::Display for CubeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Display for CubeCounts {
+    /// Writes the counts like '3 blue, 4 red'
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, (colour, count)) in self.counts.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}", count, colour)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single line of the game log, the game number and every pull from the bag
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Game {
+    pub id: u32,
+    pub pulls: Vec<CubeCounts>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseGameError {
+    message: String,
+}
+
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Game {
+    /// Parses a line like 'Game 1: 3 blue, 4 red; 1 red', interning the colours into the palette
+    pub fn parse(input: &str, palette: &mut Palette) -> Result<Game, ParseGameError> {
+        let (game_str, remainder) = input.split_once(':').ok_or_else(|| ParseGameError {
+            message: format!("'{}' is missing the ':' after the game number", input),
+        })?;
+        let id = parse_game_segment(game_str)?;
+        let pulls = parse_pulls_segment(remainder, palette)?;
+        Ok(Game { id, pulls })
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseGameError;
+
+    fn from_str(input: &str) -> Result<Game, Self::Err> {
+        Game::parse(input, &mut Palette::default())
+    }
+}
+
+impl fmt::Display for Game {
+    /// Writes the game in the puzzle format, so parsing the output gives the same game
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game {}: ", self.id)?;
+        for (idx, pull) in self.pulls.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", pull)?;
+        }
+        Ok(())
+    }
+}
+
+/// The bag from the puzzle
+const DEFAULT_BAG: &str = "red=12,green=13,blue=14";
+
+/// Reads the bag from `--bag` or the file named by `--bag-file`, the puzzle's bag otherwise
+fn bag_spec_from_options(options: &[String]) -> Result<String, CubeError> {
+    let spec = options::read_spec(options, "--bag", "--bag-file")
+        .map_err(|x| CubeError { message: x.message })?;
+    Ok(spec.unwrap_or_else(|| DEFAULT_BAG.to_string()))
 }
 
-#[derive(Eq, PartialEq, Debug)]
-struct CubeCounts {
-    red: u32,
-    green: u32,
-    blue: u32,
+/// Parses `colour=count` entries with `options::parse_key_values`
+fn parse_bag(spec: &str, palette: &mut Palette) -> Result<CubeCounts, CubeError> {
+    let mut bag = CubeCounts::default();
+    let entries = options::parse_key_values(spec, "colour=count")
+        .map_err(|x| CubeError { message: x.message })?;
+    for (colour, count) in entries {
+        if colour.is_empty() {
+            return Err(CubeError {
+                message: format!("'={}' is missing a colour", count),
+            });
+        }
+        let count = count.parse::<u32>().map_err(|x| CubeError {
+            message: format!("'{}={}' has an invalid count: {}", colour, count, x),
+        })?;
+        if palette.get(colour).is_some() {
+            return Err(CubeError {
+                message: format!("the bag lists '{}' more than once", colour),
+            });
+        }
+        bag.set(palette.intern(colour), count);
+    }
+    Ok(bag)
+}
+
+/// Parses every game, interning the colours into one palette
+fn parse_games(lines: &mut dyn Iterator<Item = String>) -> Result<(Palette, Vec<Game>), CubeError> {
+    let mut palette = Palette::default();
+    let games = lines
+        .enumerate()
+        .map(|(idx, x)| {
+            Game::parse(x.as_str(), &mut palette).map_err(|err| CubeError {
+                message: format!("line {}: {}", idx + 1, err),
+            })
+        })
+        .collect::<Result<Vec<Game>, CubeError>>()?;
+    Ok((palette, games))
+}
+
+/// Parses the bag and checks it only names colours that appear in the games
+fn parse_constraint(bag_spec: &str, palette: &Palette) -> Result<CubeCounts, CubeError> {
+    let constraint = parse_bag(bag_spec, &mut Palette::default())?;
+    check_constraint(&constraint, palette)?;
+    Ok(constraint)
 }
 
 /// Inspects the data and of the game and if it is possible given the constraint the sum the game number
-fn day02_part1_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
-    let constraint = CubeCounts {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
-    let total: u32 = lines
-        .map(|x| day02_part1_line_handler(x.as_str(), &constraint))
+fn day02_part1_handler(
+    lines: &mut dyn Iterator<Item = String>,
+    bag_spec: &str,
+) -> Result<u32, CubeError> {
+    let (palette, games) = parse_games(lines)?;
+    let constraint = parse_constraint(bag_spec, &palette)?;
+    let total: u32 = games
+        .iter()
+        .map(|game| day02_part1_game_handler(game, &constraint))
         .sum();
-    total
+    Ok(total)
+}
+
+/// A colour in a pull that exceeds what the bag holds. `pull` is 1-based.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Violation {
+    pull: usize,
+    colour: Colour,
+    count: u32,
+    limit: u32,
+}
+
+/// Every limit a game breaks, none when the game is possible
+fn find_violations(pulls: &[CubeCounts], constraint: &CubeCounts) -> Vec<Violation> {
+    pulls
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, pull)| {
+            pull.counts
+                .iter()
+                .filter(|(colour, count)| *count > constraint.get(colour))
+                .map(move |(colour, count)| Violation {
+                    pull: idx + 1,
+                    colour: colour.clone(),
+                    count: *count,
+                    limit: constraint.get(colour),
+                })
+        })
+        .collect()
+}
+
+/// Lists the possible games on the first line, then one line per pull that breaks the bag
+fn day02_query_handler(
+    lines: &mut dyn Iterator<Item = String>,
+    bag_spec: &str,
+) -> Result<Vec<String>, CubeError> {
+    let (palette, games) = parse_games(lines)?;
+    let constraint = parse_constraint(bag_spec, &palette)?;
+    let mut possible: Vec<String> = Vec::new();
+    let mut report: Vec<String> = Vec::new();
+    games.iter().for_each(|game| {
+        let game_id = game.id;
+        let violations = find_violations(&game.pulls, &constraint);
+        if violations.is_empty() {
+            possible.push(game_id.to_string());
+        }
+        let mut remaining = violations.as_slice();
+        while let Some(first) = remaining.first() {
+            let same_pull = remaining
+                .iter()
+                .take_while(|x| x.pull == first.pull)
+                .count();
+            let limits: Vec<String> = remaining[..same_pull]
+                .iter()
+                .map(|x| format!("{} {} > {}", x.count, x.colour, x.limit))
+                .collect();
+            report.push(format!(
+                "Game {} pull {}: {}",
+                game_id,
+                first.pull,
+                limits.join(", ")
+            ));
+            remaining = &remaining[same_pull..];
+        }
+    });
+    report.insert(0, format!("Possible games: {}", possible.join(", ")));
+    Ok(report)
 }
 
-/// Parse the input into a PullResult and compare against the constraint
+/// Fails when the constraint names a colour that never appears in the games
+fn check_constraint(constraint: &CubeCounts, palette: &Palette) -> Result<(), CubeError> {
+    match constraint
+        .counts
+        .iter()
+        .find(|(colour, _)| palette.get(colour).is_none())
+    {
+        Some((colour, _)) => Err(CubeError {
+            message: format!("the constraint references unknown colour '{}'", colour),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Compare the pulls of a game against the constraint.
 /// Return 0 if the game cannot meet the constraint
-fn day02_part1_line_handler(input: &str, constraint: &CubeCounts) -> u32 {
-    let (game_id, pulls) = parse_game(input);
-    if pulls.iter().all(|x| {
-        x.red <= constraint.red && x.blue <= constraint.blue && x.green <= constraint.green
-    }) {
-        return game_id;
+fn day02_part1_game_handler(game: &Game, constraint: &CubeCounts) -> u32 {
+    if game.pulls.iter().all(|x| x.fits_within(constraint)) {
+        return game.id;
     }
     0
 }
 
 /// Sums all the power levels for game
-fn day02_part2_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
-    let power = lines
-        .map(|line| parse_game(line.as_str()))
-        .map(|(_game_id, pulls)| calculate_power(pulls))
-        .sum();
-    power
-}
-
-/// Iterates over the input vector to establish the minimum number of cubes for each color
-/// in the bag. Then multiplies those values together to determine the power.
-fn calculate_power(pulls: Vec<CubeCounts>) -> u32 {
-    let min_possible_cubes_in_bag = pulls.iter().fold(
-        CubeCounts {
-            red: 0,
-            green: 0,
-            blue: 0,
-        },
-        |mut acc, x| {
-            if x.red > acc.red {
-                acc.red = x.red
-            }
-            if x.green > acc.green {
-                acc.green = x.green
+fn day02_part2_handler(lines: &mut dyn Iterator<Item = String>) -> Result<u64, CubeError> {
+    let (palette, games) = parse_games(lines)?;
+    games.iter().try_fold(0u64, |acc, game| {
+        let power = game_power(game, &palette)?;
+        acc.checked_add(power).ok_or_else(|| CubeError {
+            message: "the power total overflows a u64".to_string(),
+        })
+    })
+}
+
+/// Establishes the minimum number of cubes of each colour needed in the bag for the pulls
+fn minimum_bag(pulls: &[CubeCounts]) -> CubeCounts {
+    pulls.iter().fold(CubeCounts::default(), |mut acc, x| {
+        x.counts.iter().for_each(|(colour, count)| {
+            if *count > acc.get(colour) {
+                acc.set(colour.clone(), *count)
             }
-            if x.blue > acc.blue {
-                acc.blue = x.blue
+        });
+        acc
+    })
+}
+
+/// Multiplies the minimum number of cubes of every colour in the palette together to
+/// determine the power. A colour the game never pulls contributes 0.
+/// None when the product does not fit in a u64.
+fn calculate_power(pulls: &[CubeCounts], palette: &Palette) -> Option<u64> {
+    let min_possible_cubes_in_bag = minimum_bag(pulls);
+    palette.colours().iter().try_fold(1u64, |acc, colour| {
+        acc.checked_mul(min_possible_cubes_in_bag.get(colour) as u64)
+    })
+}
+
+/// `calculate_power` for a game, naming the game when the power overflows
+fn game_power(game: &Game, palette: &Palette) -> Result<u64, CubeError> {
+    calculate_power(&game.pulls, palette).ok_or_else(|| CubeError {
+        message: format!("the power of game {} overflows a u64", game.id),
+    })
+}
+
+/// Count summary for one colour over the pulls that list it
+#[derive(Debug, PartialEq, Clone)]
+struct ColourStats {
+    colour: Colour,
+    pulls: usize,
+    min: u32,
+    max: u32,
+    mean: f64,
+}
+
+/// Summary analytics over a game log
+#[derive(Debug, PartialEq, Clone)]
+struct GameStats {
+    games: usize,
+    colours: Vec<ColourStats>,
+    /// Each power from `calculate_power` with the number of games that have it, lowest first
+    powers: Vec<(u64, usize)>,
+    /// Each game that dominates at least one other, with the games it dominates
+    dominance: Vec<(u32, Vec<u32>)>,
+}
+
+fn game_stats(games: &[Game], palette: &Palette) -> Result<GameStats, CubeError> {
+    let colours = palette
+        .colours()
+        .iter()
+        .map(|colour| {
+            let counts: Vec<u32> = games
+                .iter()
+                .flat_map(|game| game.pulls.iter())
+                .filter_map(|pull| {
+                    pull.iter()
+                        .find(|(x, _)| *x == colour)
+                        .map(|(_, count)| count)
+                })
+                .collect();
+            ColourStats {
+                colour: colour.clone(),
+                pulls: counts.len(),
+                min: counts.iter().copied().min().unwrap_or(0),
+                max: counts.iter().copied().max().unwrap_or(0),
+                mean: counts.iter().map(|x| *x as f64).sum::<f64>() / counts.len().max(1) as f64,
             }
-            acc
-        },
-    );
-    // Not required: if one of these values is zero that would be bad. if need, put into a vec and filter out the zeros
-    //  and calculate the product
-    min_possible_cubes_in_bag.red * min_possible_cubes_in_bag.green * min_possible_cubes_in_bag.blue
-}
-
-/// Parses the input into a tuple of the game_id and a vector of pulls
-fn parse_game(input: &str) -> (u32, Vec<CubeCounts>) {
-    // Extract the game id.
-    let mut splits = input.split(":");
-    let game_str = splits.next().expect("First segments should be Game #");
-    let remainder = splits
-        .next()
-        .expect("Second segment should be a list of Pull");
-    let game_id = parse_game_segment(game_str);
-    let pulls = parse_pulls_segment(remainder);
-    (game_id, pulls)
+        })
+        .collect();
+
+    let mut powers: Vec<(u64, usize)> = Vec::new();
+    let mut sorted_powers: Vec<u64> = games
+        .iter()
+        .map(|game| game_power(game, palette))
+        .collect::<Result<Vec<u64>, CubeError>>()?;
+    sorted_powers.sort();
+    sorted_powers
+        .iter()
+        .for_each(|power| match powers.last_mut() {
+            Some((last, count)) if last == power => *count += 1,
+            _ => powers.push((*power, 1)),
+        });
+
+    let bags: Vec<CubeCounts> = games.iter().map(|game| minimum_bag(&game.pulls)).collect();
+    let dominance = games
+        .iter()
+        .zip(bags.iter())
+        .map(|(game, bag)| {
+            let dominated: Vec<u32> = games
+                .iter()
+                .zip(bags.iter())
+                .filter(|(_, other)| dominates(bag, other, palette))
+                .map(|(other_game, _)| other_game.id)
+                .collect();
+            (game.id, dominated)
+        })
+        .filter(|(_, dominated)| !dominated.is_empty())
+        .collect();
+
+    Ok(GameStats {
+        games: games.len(),
+        colours,
+        powers,
+        dominance,
+    })
+}
+
+/// True when bag `a` needs at least as many cubes of every colour as `b`, and more of one
+fn dominates(a: &CubeCounts, b: &CubeCounts, palette: &Palette) -> bool {
+    let colours = palette.colours();
+    colours.iter().all(|x| a.get(x) >= b.get(x)) && colours.iter().any(|x| a.get(x) > b.get(x))
+}
+
+impl GameStats {
+    /// Lines of the colour, power and dominance tables
+    fn to_table(&self) -> Vec<String> {
+        let width = self
+            .colours
+            .iter()
+            .map(|x| x.colour.len())
+            .chain(["Colour".len()])
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![
+            format!("Games {}", self.games),
+            String::new(),
+            format!(
+                "{:<width$}  {:>5}  {:>5}  {:>5}  {:>7}",
+                "Colour", "Pulls", "Min", "Max", "Mean"
+            ),
+        ];
+        self.colours.iter().for_each(|x| {
+            lines.push(format!(
+                "{:<width$}  {:>5}  {:>5}  {:>5}  {:>7.2}",
+                x.colour, x.pulls, x.min, x.max, x.mean
+            ))
+        });
+
+        lines.push(String::new());
+        lines.push(format!("{:>10}  {:>5}", "Power", "Games"));
+        self.powers
+            .iter()
+            .for_each(|(power, count)| lines.push(format!("{:>10}  {:>5}", power, count)));
+
+        lines.push(String::new());
+        if self.dominance.is_empty() {
+            lines.push("No game dominates another".to_string());
+        }
+        self.dominance.iter().for_each(|(game_id, dominated)| {
+            let ids: Vec<String> = dominated.iter().map(|x| x.to_string()).collect();
+            lines.push(format!("Game {} dominates {}", game_id, ids.join(", ")));
+        });
+        lines
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("games", Json::from(self.games)),
+            (
+                "colours",
+                Json::Array(
+                    self.colours
+                        .iter()
+                        .map(|x| {
+                            Json::object([
+                                ("colour", Json::string(x.colour.as_ref())),
+                                ("pulls", Json::from(x.pulls)),
+                                ("min", Json::from(x.min)),
+                                ("max", Json::from(x.max)),
+                                ("mean", Json::from(x.mean)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "powers",
+                Json::Array(
+                    self.powers
+                        .iter()
+                        .map(|(power, count)| {
+                            Json::object([
+                                ("power", Json::from(*power)),
+                                ("games", Json::from(*count)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "dominance",
+                Json::Array(
+                    self.dominance
+                        .iter()
+                        .map(|(game_id, dominated)| {
+                            Json::object([
+                                ("game", Json::from(*game_id)),
+                                ("dominates", Json::from(dominated.clone())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
 }
 
 /// Excepts a string in the format of 'Game #' and returns the # as a u32
-fn parse_game_segment(game_str: &str) -> u32 {
-    let game_str = game_str.trim();
-    let mut splits = game_str.split(" ");
-    splits.next().expect("Don't need the game segment");
-    let game_id = splits.next().expect("This should be the int portion");
-    game_id.parse::<u32>().expect("Should parse into int")
+fn parse_game_segment(game_str: &str) -> Result<u32, ParseGameError> {
+    let game_id = game_str
+        .trim()
+        .strip_prefix("Game ")
+        .ok_or_else(|| ParseGameError {
+            message: format!("'{}' should look like 'Game #'", game_str.trim()),
+        })?;
+    game_id.trim().parse::<u32>().map_err(|x| ParseGameError {
+        message: format!("'{}' is not a valid game number: {}", game_id.trim(), x),
+    })
 }
 
-/// Excepts a str in the format of a repeating '# (red|blue|green),' values separated by a semicolon
-fn parse_pulls_segment(game_str: &str) -> Vec<CubeCounts> {
+/// Excepts a str in the format of a repeating '# colour,' values separated by a semicolon
+fn parse_pulls_segment(
+    game_str: &str,
+    palette: &mut Palette,
+) -> Result<Vec<CubeCounts>, ParseGameError> {
     let pulls = game_str.split(";");
-    pulls.map(parse_to_pull_result).collect()
-}
-
-/// Except a string of '# (red|blue|green)' separated by a comma and return a PullResult
-fn parse_to_pull_result(pull: &str) -> CubeCounts {
-    let mut result = CubeCounts {
-        red: 0,
-        green: 0,
-        blue: 0,
-    };
-    pull.split(",").for_each(|mut x| {
-        x = x.trim();
-        let mut splits = x.split(" ");
-        let value = splits.next().expect("Should be an int").trim();
-        let color = splits.next().expect("Should be a color").trim();
-        match color {
-            "red" => {
-                result.red = value.parse::<u32>().expect("Should have been an u32");
-            }
-            "green" => {
-                result.green = value.parse::<u32>().expect("Should have been an u32");
-            }
-            "blue" => {
-                result.blue = value.parse::<u32>().expect("Should have been an u32");
-            }
-            _ => panic!("Color {} is unhandled", color),
+    pulls.map(|x| parse_to_pull_result(x, palette)).collect()
+}
+
+/// Except a string of '# colour' separated by a comma and return a PullResult.
+/// A colour may have more than one word but may only appear once in a pull.
+fn parse_to_pull_result(pull: &str, palette: &mut Palette) -> Result<CubeCounts, ParseGameError> {
+    let mut result = CubeCounts::default();
+    for x in pull.split(",").map(str::trim) {
+        let (value, color) = x.split_once(" ").ok_or_else(|| ParseGameError {
+            message: format!("'{}' should look like '# colour'", x),
+        })?;
+        let value = value.parse::<u32>().map_err(|err| ParseGameError {
+            message: format!("'{}' has an invalid count: {}", x, err),
+        })?;
+        let color = color.trim();
+        if result.counts.iter().any(|(c, _)| c.as_ref() == color) {
+            return Err(ParseGameError {
+                message: format!("'{}' appears more than once in '{}'", color, pull.trim()),
+            });
         }
-    });
-    result
+        result.set(palette.intern(color), value);
+    }
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::xorshift::XorShift;
 
-    #[test]
-    fn test_day02_part1() {
-        let v = vec![
+    fn sample() -> Vec<String> {
+        vec![
             "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green".to_string(),
             "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue".to_string(),
             "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red".to_string(),
             "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red".to_string(),
             "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green".to_string(),
-        ];
-        let mut itr = v.into_iter();
-        assert_eq!(8u32, day02_part1_handler(&mut itr));
+        ]
+    }
+
+    fn counts(palette: &mut Palette, entries: &[(&str, u32)]) -> CubeCounts {
+        let mut result = CubeCounts::default();
+        entries
+            .iter()
+            .for_each(|(colour, count)| result.set(palette.intern(colour), *count));
+        result
+    }
+
+    #[test]
+    fn test_day02_part1() {
+        let mut itr = sample().into_iter();
+        assert_eq!(Ok(8u32), day02_part1_handler(&mut itr, DEFAULT_BAG));
+    }
+
+    #[test]
+    fn test_day02_part1_custom_bag() {
+        let mut itr = sample().into_iter();
+        assert_eq!(
+            Ok(1 + 2 + 3 + 4 + 5),
+            day02_part1_handler(&mut itr, "blue=15\n# plenty of red\nred=20\ngreen=13")
+        );
+        let mut itr = sample().into_iter();
+        assert_eq!(Ok(2), day02_part1_handler(&mut itr, "red=1,green=3,blue=4"));
+    }
+
+    #[test]
+    fn test_parse_bag() {
+        let mut palette = Palette::default();
+        let bag = parse_bag(" red = 12 , green=13,blue=14", &mut palette).unwrap();
+        assert_eq!(
+            counts(&mut palette, &[("red", 12), ("green", 13), ("blue", 14)]),
+            bag
+        );
+        assert!(parse_bag("red", &mut Palette::default()).is_err());
+        assert!(parse_bag("red=lots", &mut Palette::default()).is_err());
+        assert!(parse_bag("=12", &mut Palette::default()).is_err());
+        assert_eq!(
+            "the bag lists 'red' more than once",
+            parse_bag("red=1,red=2", &mut Palette::default())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_bag_spec_from_options() {
+        assert_eq!(Ok(DEFAULT_BAG.to_string()), bag_spec_from_options(&[]));
+        let options = vec!["--bag".to_string(), "red=1".to_string()];
+        assert_eq!(Ok("red=1".to_string()), bag_spec_from_options(&options));
+        let options = vec!["--bag-file=./does/not/exist.txt".to_string()];
+        assert!(bag_spec_from_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_day02_query() {
+        let mut itr = sample().into_iter();
+        let report = day02_query_handler(&mut itr, DEFAULT_BAG).unwrap();
+        assert_eq!(
+            vec![
+                "Possible games: 1, 2, 5",
+                "Game 3 pull 1: 20 red > 12",
+                "Game 4 pull 3: 15 blue > 14, 14 red > 12",
+            ],
+            report
+        );
+
+        let mut itr = sample().into_iter();
+        let report = day02_query_handler(&mut itr, "red=3,green=3,blue=6").unwrap();
+        assert_eq!(
+            vec![
+                "Possible games: 2",
+                "Game 1 pull 1: 4 red > 3",
+                "Game 3 pull 1: 8 green > 3, 20 red > 3",
+                "Game 3 pull 2: 4 red > 3, 13 green > 3",
+                "Game 3 pull 3: 5 green > 3",
+                "Game 4 pull 2: 6 red > 3",
+                "Game 4 pull 3: 15 blue > 6, 14 red > 3",
+                "Game 5 pull 1: 6 red > 3",
+            ],
+            report
+        );
+    }
+
+    #[test]
+    fn test_day02_part1_unknown_colour() {
+        // No blue cubes are ever pulled, so the default constraint cannot be checked
+        let mut itr = vec!["Game 1: 3 red, 4 green".to_string()].into_iter();
+        assert_eq!(
+            "the constraint references unknown colour 'blue'",
+            day02_part1_handler(&mut itr, DEFAULT_BAG)
+                .unwrap_err()
+                .to_string()
+        );
     }
 
     #[test]
     fn test_day02_part2() {
-        let v = vec![
-            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green".to_string(),
-            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue".to_string(),
-            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red".to_string(),
-            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red".to_string(),
-            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green".to_string(),
+        let mut itr = sample().into_iter();
+        assert_eq!(Ok(2286u64), day02_part2_handler(&mut itr));
+    }
+
+    #[test]
+    fn test_other_colours() {
+        let mut palette = Palette::default();
+        let game = Game::parse(
+            "Game 7: 2 cyan, 3 magenta; 5 yellow, 1 cyan; 4 magenta, 2 black",
+            &mut palette,
+        )
+        .unwrap();
+        assert_eq!(4, palette.colours().len());
+        assert_eq!(
+            counts(
+                &mut palette,
+                &[("cyan", 2), ("magenta", 4), ("yellow", 5), ("black", 2)]
+            ),
+            minimum_bag(&game.pulls)
+        );
+        assert_eq!(Some(2 * 4 * 5 * 2), calculate_power(&game.pulls, &palette));
+
+        let bag = counts(&mut palette, &[("cyan", 2), ("magenta", 4), ("yellow", 5)]);
+        assert_eq!(0, day02_part1_game_handler(&game, &bag));
+        let bag = counts(
+            &mut palette,
+            &[("cyan", 2), ("magenta", 4), ("yellow", 5), ("black", 9)],
+        );
+        assert_eq!(7, day02_part1_game_handler(&game, &bag));
+    }
+
+    #[test]
+    fn test_power_with_missing_colour() {
+        let mut palette = Palette::default();
+        let first = Game::parse("Game 1: 2 red, 3 green", &mut palette).unwrap();
+        let second = Game::parse("Game 2: 2 red, 3 blue", &mut palette).unwrap();
+        assert_eq!(Some(0), calculate_power(&first.pulls, &palette));
+        assert_eq!(Some(0), calculate_power(&second.pulls, &palette));
+    }
+
+    #[test]
+    fn test_power_overflow() {
+        // Ten colours of 100 cubes give 10^20, more than a u64 holds
+        let pull: Vec<String> = (0..10).map(|x| format!("100 c{}", x)).collect();
+        let lines = vec![
+            "Game 1: 2 c0".to_string(),
+            format!("Game 2: {}", pull.join(", ")),
         ];
-        let mut itr = v.into_iter();
-        assert_eq!(2286u32, day02_part2_handler(&mut itr));
+        assert_eq!(
+            "the power of game 2 overflows a u64",
+            day02_part2_handler(&mut lines.into_iter())
+                .unwrap_err()
+                .to_string()
+        );
+
+        // Five colours of about 100 cubes fit
+        let lines = vec!["Game 1: 100 a, 101 b, 102 c, 103 d, 104 e".to_string()];
+        assert_eq!(
+            Ok(100 * 101 * 102 * 103 * 104),
+            day02_part2_handler(&mut lines.into_iter())
+        );
+    }
+
+    #[test]
+    fn test_palette_interns() {
+        let mut palette = Palette::default();
+        let red = palette.intern("red");
+        let again = palette.intern("red");
+        assert!(Rc::ptr_eq(&red, &again));
+        assert_eq!(1, palette.colours().len());
+        assert_eq!(None, palette.get("blue"));
     }
 
     #[test]
     fn test_parse_game() {
+        let mut palette = Palette::default();
         let game = "Game 21: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let (game_id, pull_results) = parse_game(game);
-        assert_eq!(21u32, game_id);
-        assert_eq!(3, pull_results.len());
+        let game = Game::parse(game, &mut palette).unwrap();
+        assert_eq!(21u32, game.id);
+        assert_eq!(3, game.pulls.len());
     }
 
     #[test]
     fn test_parse_game_segment() {
-        assert_eq!(0u32, parse_game_segment(" Game 0 "));
-        assert_eq!(1u32, parse_game_segment("\tGame 1\n"));
+        assert_eq!(Ok(0u32), parse_game_segment(" Game 0 "));
+        assert_eq!(Ok(1u32), parse_game_segment("\tGame 1\n"));
         assert_eq!(
-            u32::MAX,
+            Ok(u32::MAX),
             parse_game_segment(format!("Game {}", u32::MAX).as_str())
         );
     }
 
     #[test]
     fn test_parse_to_pull_result() {
+        let mut palette = Palette::default();
+        let expected = counts(&mut palette, &[("red", 1)]);
         assert_eq!(
-            CubeCounts {
-                red: 1,
-                green: 0,
-                blue: 0
-            },
-            parse_to_pull_result(" 1 red ")
+            expected,
+            parse_to_pull_result(" 1 red ", &mut palette).unwrap()
         );
+        let expected = counts(&mut palette, &[("green", 1)]);
         assert_eq!(
-            CubeCounts {
-                red: 0,
-                green: 1,
-                blue: 0
-            },
-            parse_to_pull_result(" 1 green ")
+            expected,
+            parse_to_pull_result(" 1 green ", &mut palette).unwrap()
         );
+        let expected = counts(&mut palette, &[("blue", 1)]);
         assert_eq!(
-            CubeCounts {
-                red: 0,
-                green: 0,
-                blue: 1
-            },
-            parse_to_pull_result(" 1 blue ")
+            expected,
+            parse_to_pull_result(" 1 blue ", &mut palette).unwrap()
         );
+        let expected = counts(&mut palette, &[("blue", 69), ("red", 13), ("green", 42)]);
+        let result =
+            parse_to_pull_result("\t69 blue  , 13 red  \t, 42 green \n", &mut palette).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(13, result.get("red"));
+        assert_eq!(0, result.get("violet"));
+    }
+
+    #[test]
+    fn test_game_display() {
+        sample().iter().for_each(|line| {
+            let game = Game::from_str(line).unwrap();
+            assert_eq!(*line, game.to_string());
+        });
+        let game = Game::from_str("  Game 3 :7 dark blue,0 red ;  1 green").unwrap();
+        assert_eq!("Game 3: 7 dark blue, 0 red; 1 green", game.to_string());
+        assert_eq!(7, game.pulls[0].get("dark blue"));
+    }
+
+    #[test]
+    fn test_game_from_str_errors() {
+        let errors = [
+            ("3 blue, 4 red", "is missing the ':'"),
+            ("Round 1: 3 blue", "should look like 'Game #'"),
+            ("Game x: 3 blue", "is not a valid game number"),
+            ("Game 1: blue", "should look like '# colour'"),
+            ("Game 1: three blue", "has an invalid count"),
+            ("Game 1: 3 blue; ", "should look like '# colour'"),
+            ("Game 1: 3 blue, 4 blue", "'blue' appears more than once"),
+        ];
+        errors.iter().for_each(|(input, expected)| {
+            let message = Game::from_str(input).unwrap_err().to_string();
+            assert!(message.contains(expected), "{}: {}", input, message);
+        });
+
+        let mut itr = vec!["Game 1: 3 blue".to_string(), "Game 2:".to_string()].into_iter();
+        assert!(day02_part2_handler(&mut itr)
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 2: "));
+    }
+
+    /// Games with random ids, colours and counts in the puzzle format
+    fn random_games(count: usize) -> Vec<String> {
+        let colours = ["red", "green", "blue", "cyan", "dark blue", "ultra violet"];
+        let mut random = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        let mut next = move |bound: u64| random.below(bound);
+        (0..count)
+            .map(|_| {
+                let pulls: Vec<String> = (0..(1 + next(5)))
+                    .map(|_| {
+                        let offset = next(colours.len() as u64) as usize;
+                        let entries: Vec<String> = (0..(1 + next(colours.len() as u64)))
+                            .map(|idx| {
+                                let colour = colours[(offset + idx as usize) % colours.len()];
+                                format!("{} {}", next(25), colour)
+                            })
+                            .collect();
+                        entries.join(", ")
+                    })
+                    .collect();
+                format!("Game {}: {}", next(u32::MAX as u64 + 1), pulls.join("; "))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_game_round_trip() {
+        random_games(500).iter().for_each(|line| {
+            // print -> parse gives back the text, parse -> print -> parse gives back the game
+            let game = Game::from_str(line).unwrap();
+            let printed = game.to_string();
+            assert_eq!(*line, printed);
+            assert_eq!(game, Game::from_str(&printed).unwrap());
+        });
+    }
+
+    #[test]
+    fn test_game_stats() {
+        let mut itr = sample().into_iter();
+        let (palette, games) = parse_games(&mut itr).unwrap();
+        let stats = game_stats(&games, &palette).unwrap();
+        assert_eq!(5, stats.games);
+        // Colours in the order they first appear: blue, red, green
         assert_eq!(
-            CubeCounts {
-                red: 13,
-                green: 42,
-                blue: 69
+            ColourStats {
+                colour: Rc::from("red"),
+                pulls: 11,
+                min: 1,
+                max: 20,
+                mean: 61.0 / 11.0,
             },
-            parse_to_pull_result("\t69 blue  , 13 red  \t, 42 green \n")
+            stats.colours[1]
+        );
+        assert_eq!(
+            vec![(12, 1), (36, 1), (48, 1), (630, 1), (1560, 1)],
+            stats.powers
+        );
+        // Game 3 needs 20 red, 13 green, 6 blue and game 4 needs 14 red, 3 green, 15 blue
+        assert_eq!(
+            vec![(3, vec![1, 2, 5]), (4, vec![1, 2, 5])],
+            stats.dominance
+        );
+    }
+
+    #[test]
+    fn test_game_stats_output() {
+        let mut itr = vec![
+            "Game 1: 1 red, 2 blue".to_string(),
+            "Game 2: 3 red; 4 blue".to_string(),
+            "Game 3: 3 red, 4 blue".to_string(),
+        ]
+        .into_iter();
+        let (palette, games) = parse_games(&mut itr).unwrap();
+        let stats = game_stats(&games, &palette).unwrap();
+        assert_eq!(
+            vec![
+                "Games 3",
+                "",
+                "Colour  Pulls    Min    Max     Mean",
+                "red         3      1      3     2.33",
+                "blue        3      2      4     3.33",
+                "",
+                "     Power  Games",
+                "         2      1",
+                "        12      2",
+                "",
+                "Game 2 dominates 1",
+                "Game 3 dominates 1",
+            ],
+            stats.to_table()
+        );
+        assert_eq!(
+            concat!(
+                r#"{"games":3,"colours":["#,
+                r#"{"colour":"red","pulls":3,"min":1,"max":3,"mean":2.3333333333333335},"#,
+                r#"{"colour":"blue","pulls":3,"min":2,"max":4,"mean":3.3333333333333335}],"#,
+                r#""powers":[{"power":2,"games":1},{"power":12,"games":2}],"#,
+                r#""dominance":[{"game":2,"dominates":[1]},{"game":3,"dominates":[1]}]}"#
+            ),
+            stats.to_json().to_string()
         );
     }
 }
@@ -1,171 +1,247 @@
-use std::ops::Range;
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    ops::Range,
+};
 
-/// Pretty print the result of the calculations
-pub fn day03_part1(lines: &mut dyn Iterator<Item = String>) {
-    let total = day03_part1_handler(lines);
+use super::json::Json;
+use crate::io::options;
+
+/// Pretty print the result of the calculations.
+/// Every day03 scenario accepts the options read by `SchematicRules::from_options`.
+pub fn day03_part1(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let rules = SchematicRules::from_options(options).map_err(|x| x.to_string())?;
+    let total = day03_part1_handler(lines, &rules).map_err(|x| x.to_string())?;
     println!("Total: {}", total);
+    Ok(())
 }
 
-fn day03_part1_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
-    let mut scan_area = ScanArea {
-        prev: None,
-        current: None,
-        next: lines.next(),
-    };
-
-    let mut total = 0u32;
-    loop {
-        let line = lines.next();
-        scan_area = scan_area.shift_new(line);
-
-        // Processed the last item
-        if scan_area.current.is_none() {
-            break;
-        }
-        total += scan_area_handler(scan_area.clone());
-    }
-    total
+/// Sums the numbers within the rules' radius of any symbol
+fn day03_part1_handler(
+    lines: &mut dyn Iterator<Item = String>,
+    rules: &SchematicRules,
+) -> Result<u64, SchematicError> {
+    let grid = SchematicGrid::new(lines, rules)?;
+    Ok(grid
+        .numbers_near_symbols(rules.radius)
+        .iter()
+        .map(|x| grid.numbers()[*x].value as u64)
+        .sum())
 }
 
-pub fn day03_part2(lines: &mut dyn Iterator<Item = String>) {
-    let total = day03_part2_handler(lines);
+pub fn day03_part2(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    // Part 2 only counts gears, so the gear has to be one of the symbols
+    let rules = SchematicRules::from_options(options)
+        .and_then(|rules| rules.check_gear().map(|_| rules))
+        .map_err(|x| x.to_string())?;
+    let total = day03_part2_handler(lines, &rules).map_err(|x| x.to_string())?;
     println!("Total: {}", total);
+    Ok(())
 }
 
-fn day03_part2_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
-    let mut scan_area = ScanArea {
-        prev: None,
-        current: None,
-        next: lines.next(),
-    };
-
-    let mut total = 0u32;
-    loop {
-        let line = lines.next();
-        scan_area = scan_area.shift_new(line);
-
-        // Processed the last item
-        if scan_area.current.is_none() {
-            break;
-        }
-        total += scan_area_handler2(scan_area.clone());
-    }
-    total
+/// Sums the ratios of the gears the rules count
+fn day03_part2_handler(
+    lines: &mut dyn Iterator<Item = String>,
+    rules: &SchematicRules,
+) -> Result<u64, SchematicError> {
+    let grid = SchematicGrid::new(lines, rules)?;
+    ratio_total(&gear_reports(&grid, rules)?)
 }
 
-fn scan_area_handler(scan_area: ScanArea) -> u32 {
-    let current_line = scan_area.current.expect("There should always be a current");
-    let part_number_locations = extract_part_number_locations(&current_line);
-    let prev_gears = if scan_area.prev.is_some() {
-        extract_gears(scan_area.prev.unwrap().as_str())
+/// Prints every number with the symbols it touches and every gear with its parts,
+/// as text or as JSON with `--json`
+pub fn day03_report(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let rules = SchematicRules::from_options(options).map_err(|x| x.to_string())?;
+    let report = schematic_report(lines, &rules).map_err(|x| x.to_string())?;
+    if options::has_flag(options, "--json") {
+        println!("{}", report.to_json());
     } else {
-        Vec::new()
-    };
-
-    let curr_gears = extract_gears(&current_line);
+        report.to_text().iter().for_each(|x| println!("{}", x));
+    }
+    Ok(())
+}
 
-    let next_gears = if scan_area.next.is_some() {
-        extract_gears(scan_area.next.unwrap().as_str())
+/// Re-prints the schematic with counted part numbers, uncounted numbers and gears
+/// highlighted. Only gears with exactly two parts are highlighted unless `--gear-parts`
+/// picks another rule, the legend names the rule. Uses ANSI colours on a terminal and marker
+/// lines otherwise, or always with `--plain`.
+pub fn day03_render(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let rules = render_rules(options).map_err(|x| x.to_string())?;
+    let lines: Vec<String> = lines.collect();
+    let report = schematic_report(&mut lines.iter().cloned(), &rules).map_err(|x| x.to_string())?;
+    let style = if options::has_flag(options, "--plain") || !io::stdout().is_terminal() {
+        RenderStyle::Plain
     } else {
-        Vec::new()
+        RenderStyle::Ansi
     };
-    let part_numbers: Vec<&PartNumberLocation> = part_number_locations
+    render_schematic(&lines, &report, &rules, style)
         .iter()
-        .filter(|x| {
-            let mut gear_chain = prev_gears
-                .iter()
-                .chain(curr_gears.iter())
-                .chain(next_gears.iter());
-            part_intersects_gears(x, &mut gear_chain)
-        })
-        .collect();
-    let result: u32 = part_numbers.iter().map(|x| x.value).sum();
-    result
+        .for_each(|x| println!("{}", x));
+    Ok(())
 }
 
-fn scan_area_handler2(scan_area: ScanArea) -> u32 {
-    let current_line = scan_area.current.expect("There should always be a current");
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SchematicError {
+    message: String,
+}
 
-    let gears = extract_gears(&current_line);
-    let prev_parts = if scan_area.prev.is_some() {
-        extract_part_number_locations(scan_area.prev.unwrap().as_str())
-    } else {
-        Vec::new()
-    };
-    let curr_parts = extract_part_number_locations(&current_line);
-    let next_parts = if scan_area.next.is_some() {
-        extract_part_number_locations(scan_area.next.unwrap().as_str())
-    } else {
-        Vec::new()
-    };
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
 
-    let gear_ratios: Vec<u32> = gears
-        .iter()
-        .map(|gear| {
-            let mut part_chain = prev_parts
-                .iter()
-                .chain(curr_parts.iter())
-                .chain(next_parts.iter());
-            gear_ratio(gear, &mut part_chain)
-        })
-        .collect();
+/// Which characters count as symbols
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolSet {
+    /// Anything other than an ASCII letter, digit or '.', the puzzle's rule
+    Punctuation,
+    /// Only the listed characters
+    Only(Vec<char>),
+}
 
-    let result: u32 = gear_ratios.iter().sum();
-    result
+impl SymbolSet {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            SymbolSet::Punctuation => !(c.is_ascii_alphanumeric() || c == '.'),
+            SymbolSet::Only(symbols) => symbols.contains(&c),
+        }
+    }
 }
 
-/// Accepts a part location and gears, returns true if the part intersects any gears
-fn part_intersects_gears(
-    part_number_location: &PartNumberLocation,
-    gears: &mut dyn Iterator<Item = &GearLocation>,
-) -> bool {
-    // Lesson learned: gears is a gear chain, so you cannot call `any` on it? Correct.
-    #[allow(clippy::unnecessary_fold)]
-    let result: bool = gears.fold(false, |prev, gear| {
-        prev || part_number_location.extended_range().contains(&gear.index)
-    });
-    result
+/// How many adjacent parts a gear needs to count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GearParts {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl fmt::Display for GearParts {
+    /// Writes the rule as `--gear-parts` takes it, '2' or '2+'
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GearParts::Exactly(n) => write!(f, "{}", n),
+            GearParts::AtLeast(n) => write!(f, "{}+", n),
+        }
+    }
 }
 
-/// Accepts a gear, returns the parts that it is adjacent to.
-fn gear_ratio(
-    gear: &GearLocation,
-    part_numbers: &mut dyn Iterator<Item = &PartNumberLocation>,
-) -> u32 {
-    // Ignore non-gear symbols
-    if gear._symbol != '*' {
-        return 0;
+impl GearParts {
+    pub fn accepts(&self, parts: usize) -> bool {
+        match self {
+            GearParts::Exactly(n) => parts == *n,
+            GearParts::AtLeast(n) => parts >= *n,
+        }
     }
+}
 
-    // Lesson learned: part_numbers is a iter chain, so you cannot call `any` on it? Correct.
-    #[allow(clippy::unnecessary_fold)]
-    let parts_touching_gears: Vec<&PartNumberLocation> = part_numbers
-        .filter(|part| part.extended_range().contains(&gear.index))
-        .collect();
+/// The rules that classify a schematic. The default matches the puzzle: any punctuation is
+/// a symbol, a number next to a symbol is a part and a '*' with more than one adjacent part
+/// is a gear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchematicRules {
+    pub symbols: SymbolSet,
+    pub gear: char,
+    pub gear_parts: GearParts,
+    /// How many cells apart, counting king moves, a number and a symbol can be and still touch
+    pub radius: usize,
+}
 
-    if parts_touching_gears.len() <= 1 {
-        return 0;
+impl Default for SchematicRules {
+    fn default() -> SchematicRules {
+        SchematicRules {
+            symbols: SymbolSet::Punctuation,
+            gear: '*',
+            gear_parts: GearParts::AtLeast(2),
+            radius: 1,
+        }
     }
-    parts_touching_gears.iter().fold(1, |x, y| x * y.value)
 }
 
-// ScanArea
-#[derive(Debug, Eq, PartialEq, Clone)]
-struct ScanArea {
-    prev: Option<String>,
-    current: Option<String>,
-    next: Option<String>,
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RulesError {
+    message: String,
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
 }
 
-impl ScanArea {
-    /// Returns a new ScanArea by cloning the elements of the current
-    /// ScanArea and shifting all the elements to the next position.
-    fn shift_new(&self, line: Option<String>) -> ScanArea {
-        ScanArea {
-            prev: self.current.clone(),
-            current: self.next.clone(),
-            next: line.clone(),
+impl SchematicRules {
+    /// Reads `--symbols '*#+'`, `--gear '*'`, `--gear-parts N` (exactly N) or
+    /// `--gear-parts N+` (at least N) and `--radius K`. Anything not given keeps its default.
+    /// An explicit `--gear` must be one of the symbols.
+    pub fn from_options(options: &[String]) -> Result<SchematicRules, RulesError> {
+        let mut rules = SchematicRules::default();
+        if let Some(symbols) = options::flag_value(options, "--symbols") {
+            let symbols: Vec<char> = symbols.chars().collect();
+            if let Some(c) = symbols.iter().find(|x| x.is_ascii_digit() || **x == '.') {
+                return Err(RulesError {
+                    message: format!("'{}' cannot be a symbol", c),
+                });
+            }
+            rules.symbols = SymbolSet::Only(symbols);
+        }
+        if let Some(gear) = options::flag_value(options, "--gear") {
+            let mut chars = gear.chars();
+            rules.gear = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => {
+                    return Err(RulesError {
+                        message: format!("the gear should be a single character, not '{}'", gear),
+                    })
+                }
+            };
+            rules.check_gear()?;
+        }
+        if let Some(parts) = options::flag_value(options, "--gear-parts") {
+            let count = parts.strip_suffix('+').unwrap_or(parts);
+            let count = count
+                .parse::<usize>()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(|| RulesError {
+                    message: format!("'{}' should look like 2 or 2+", parts),
+                })?;
+            rules.gear_parts = if parts.ends_with('+') {
+                GearParts::AtLeast(count)
+            } else {
+                GearParts::Exactly(count)
+            };
         }
+        if let Some(radius) = options::flag_value(options, "--radius") {
+            rules.radius = radius.parse::<usize>().map_err(|err| RulesError {
+                message: format!("invalid --radius '{}': {}", radius, err),
+            })?;
+        }
+        Ok(rules)
+    }
+
+    /// Errors when the gear is not one of the symbols, so no gear could ever be found
+    pub fn check_gear(&self) -> Result<(), RulesError> {
+        if !self.symbols.contains(self.gear) {
+            return Err(RulesError {
+                message: format!(
+                    "the gear '{}' is not one of the symbols, add it to --symbols or pick another --gear",
+                    self.gear
+                ),
+            });
+        }
+        Ok(())
     }
 }
 
@@ -175,85 +251,654 @@ struct PartNumberLocation {
     value: u32,
 }
 
-impl PartNumberLocation {
-    /// Helper to get the PartNumberLocations' range expanded by one on each size
-    ///   for comparison
-    fn extended_range(&self) -> Range<usize> {
-        Range {
-            start: if self.index.start == 0 {
-                0
-            } else {
-                self.index.start - 1
-            },
-            end: self.index.end + 1,
+#[derive(Debug)]
+struct GearLocation {
+    symbol: char,
+    index: usize,
+}
+
+/// A number or symbol found on a line. Columns are byte offsets into the line.
+#[derive(Debug)]
+enum Token {
+    Number(PartNumberLocation),
+    Symbol(GearLocation),
+}
+
+/// Walks the bytes of a line once, yielding each number with its column span and each
+/// character the rules count as a symbol. Anything else is skipped like a '.'.
+/// A non-ASCII character is a single symbol at the column of its first byte.
+/// A number that does not fit in a u32 is an error.
+struct Tokenizer<'a> {
+    input: &'a str,
+    position: usize,
+    rules: &'a SchematicRules,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str, rules: &'a SchematicRules) -> Tokenizer<'a> {
+        Tokenizer {
+            input,
+            position: 0,
+            rules,
         }
     }
 }
 
-#[derive(Debug)]
-struct GearLocation {
-    _symbol: char,
-    index: usize,
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<Token, SchematicError>;
+
+    fn next(&mut self) -> Option<Result<Token, SchematicError>> {
+        let bytes = self.input.as_bytes();
+        while let Some(byte) = bytes.get(self.position) {
+            let start = self.position;
+            if byte.is_ascii_digit() {
+                while bytes.get(self.position).is_some_and(|x| x.is_ascii_digit()) {
+                    self.position += 1;
+                }
+                let digits = &self.input[start..self.position];
+                // Only overflow can fail, the run holds nothing but ASCII digits
+                let number = digits.parse::<u32>().map_err(|_| SchematicError {
+                    message: format!(
+                        "the number {} at column {} does not fit in a u32",
+                        digits, start
+                    ),
+                });
+                return Some(number.map(|value| {
+                    Token::Number(PartNumberLocation {
+                        index: Range {
+                            start,
+                            end: self.position,
+                        },
+                        value,
+                    })
+                }));
+            }
+            let symbol = self.input[start..]
+                .chars()
+                .next()
+                .expect("position is always on a char boundary");
+            self.position += symbol.len_utf8();
+            if !self.rules.symbols.contains(symbol) {
+                continue;
+            }
+            return Some(Ok(Token::Symbol(GearLocation {
+                symbol,
+                index: start,
+            })));
+        }
+        None
+    }
 }
 
-fn extract_gears(input: &str) -> Vec<GearLocation> {
-    let gears: Vec<GearLocation> = input
-        .chars()
-        .enumerate()
-        .filter(|(_idx, x)| !(x.is_ascii_alphanumeric() || *x == '.'))
-        .map(|(idx, x)| GearLocation {
-            _symbol: x,
-            index: idx,
-        })
-        .collect();
-    gears
+/// The numbers and symbols of one line, in the order they appear
+#[derive(Debug, Default)]
+struct ScannedLine {
+    numbers: Vec<PartNumberLocation>,
+    symbols: Vec<GearLocation>,
 }
 
-fn extract_part_number_locations(input: &str) -> Vec<PartNumberLocation> {
-    // replace all non-digits with space in the input
-    let mut only_the_digits: String = input
-        .chars()
-        .map(|x| if x.is_ascii_digit() { x } else { ' ' })
-        .collect();
+fn scan_line(input: &str, rules: &SchematicRules) -> Result<ScannedLine, SchematicError> {
+    let mut scanned = ScannedLine::default();
+    for token in Tokenizer::new(input, rules) {
+        match token? {
+            Token::Number(number) => scanned.numbers.push(number),
+            Token::Symbol(symbol) => scanned.symbols.push(symbol),
+        }
+    }
+    Ok(scanned)
+}
 
-    // The string should only contain digits and spaces, split on spaces and trim
-    //   to get a list of strings to find indices for.
-    let number_strings: Vec<String> = only_the_digits
-        .split(" ")
-        .map(|x| x.trim().to_string())
-        .filter(|x| !x.is_empty())
-        .collect();
+/// A symbol in the schematic. Rows and columns are 0-based.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SymbolReport {
+    pub symbol: char,
+    pub row: usize,
+    pub column: usize,
+}
 
-    // Iterate through the numbers strings. Find the range indices of the string.
-    //  Replace the number string with spaces so it is not detected again. Repeat.
-    let part_numbers: Vec<PartNumberLocation> = number_strings
-        .iter()
-        .map(|x| {
-            let idx = only_the_digits.find(x).unwrap_or_else(|| {
-                panic!(
-                    "This was already found! Looking for {}; {}!",
-                    x, only_the_digits
+/// A number in the schematic and every symbol next to it.
+/// It is a part number, and counts towards part 1, when it touches at least one symbol.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NumberReport {
+    pub value: u32,
+    pub row: usize,
+    pub columns: Range<usize>,
+    pub symbols: Vec<SymbolReport>,
+}
+
+impl NumberReport {
+    pub fn is_part(&self) -> bool {
+        !self.symbols.is_empty()
+    }
+}
+
+/// A gear symbol and the part numbers next to it, as indices into `SchematicReport::numbers`.
+/// It counts towards part 2 when the rules accept its number of parts, the ratio is the
+/// product of the parts when it counts and 0 otherwise.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GearReport {
+    pub row: usize,
+    pub column: usize,
+    pub parts: Vec<usize>,
+    pub counted: bool,
+    pub ratio: u64,
+}
+
+/// Everything day03 counts, so a wrong total can be traced back to the numbers behind it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SchematicReport {
+    pub numbers: Vec<NumberReport>,
+    pub gears: Vec<GearReport>,
+}
+
+impl SchematicReport {
+    /// The part 1 answer
+    pub fn part_total(&self) -> u64 {
+        self.numbers
+            .iter()
+            .filter(|x| x.is_part())
+            .map(|x| x.value as u64)
+            .sum()
+    }
+
+    /// The part 2 answer. `grid_report` has already checked that it fits.
+    pub fn gear_ratio_total(&self) -> u64 {
+        self.gears.iter().map(|x| x.ratio).sum()
+    }
+
+    fn to_text(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .numbers
+            .iter()
+            .map(|number| {
+                let touches: Vec<String> = number
+                    .symbols
+                    .iter()
+                    .map(|x| format!("'{}' at {}:{}", x.symbol, x.row, x.column))
+                    .collect();
+                format!(
+                    "{} {} at {}:{:?} touches {}",
+                    if number.is_part() { "Part" } else { "Number" },
+                    number.value,
+                    number.row,
+                    number.columns,
+                    if touches.is_empty() {
+                        "nothing".to_string()
+                    } else {
+                        touches.join(", ")
+                    }
                 )
-            });
-            let value: u32 = x.parse().expect("Should parse into a u32");
-            let replacement = " ".repeat(x.len());
-            only_the_digits = only_the_digits.replacen(x, replacement.as_str(), 1);
-            PartNumberLocation {
-                value,
-                index: Range {
-                    start: idx,
-                    end: idx + x.len(),
-                },
+            })
+            .collect();
+        self.gears.iter().for_each(|gear| {
+            let parts: Vec<String> = gear
+                .parts
+                .iter()
+                .map(|x| self.numbers[*x].value.to_string())
+                .collect();
+            lines.push(format!(
+                "Gear at {}:{} parts [{}] ratio {}",
+                gear.row,
+                gear.column,
+                parts.join(", "),
+                gear.ratio
+            ));
+        });
+        lines.push(format!(
+            "Part total {}, gear ratio total {}",
+            self.part_total(),
+            self.gear_ratio_total()
+        ));
+        lines
+    }
+
+    fn to_json(&self) -> Json {
+        let number_fields = |number: &NumberReport| {
+            vec![
+                ("value".to_string(), Json::from(number.value)),
+                ("row".to_string(), Json::from(number.row)),
+                ("start".to_string(), Json::from(number.columns.start)),
+                ("end".to_string(), Json::from(number.columns.end)),
+            ]
+        };
+        Json::object([
+            (
+                "numbers",
+                Json::Array(
+                    self.numbers
+                        .iter()
+                        .map(|number| {
+                            let symbols = number
+                                .symbols
+                                .iter()
+                                .map(|x| {
+                                    Json::object([
+                                        ("symbol", Json::string(x.symbol)),
+                                        ("row", Json::from(x.row)),
+                                        ("column", Json::from(x.column)),
+                                    ])
+                                })
+                                .collect();
+                            let mut fields = number_fields(number);
+                            fields.push(("part".to_string(), Json::Bool(number.is_part())));
+                            fields.push(("symbols".to_string(), Json::Array(symbols)));
+                            Json::Object(fields)
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "gears",
+                Json::Array(
+                    self.gears
+                        .iter()
+                        .map(|gear| {
+                            Json::object([
+                                ("row", Json::from(gear.row)),
+                                ("column", Json::from(gear.column)),
+                                (
+                                    "parts",
+                                    Json::Array(
+                                        gear.parts
+                                            .iter()
+                                            .map(|x| Json::Object(number_fields(&self.numbers[*x])))
+                                            .collect(),
+                                    ),
+                                ),
+                                ("counted", Json::Bool(gear.counted)),
+                                ("ratio", Json::from(gear.ratio)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            ("part_total", Json::from(self.part_total())),
+            ("gear_ratio_total", Json::from(self.gear_ratio_total())),
+        ])
+    }
+}
+
+/// Scans the whole schematic and relates every number to the symbols within the rules'
+/// radius of it
+pub fn schematic_report(
+    lines: &mut dyn Iterator<Item = String>,
+    rules: &SchematicRules,
+) -> Result<SchematicReport, SchematicError> {
+    grid_report(&SchematicGrid::new(lines, rules)?, rules)
+}
+
+/// Builds the report from a grid that is already indexed. Errors when a gear ratio or
+/// their total does not fit in a u64.
+pub fn grid_report(
+    grid: &SchematicGrid,
+    rules: &SchematicRules,
+) -> Result<SchematicReport, SchematicError> {
+    let numbers: Vec<NumberReport> = (0..grid.numbers().len())
+        .map(|id| {
+            let number = &grid.numbers()[id];
+            let symbols = grid
+                .symbols_near_number(id, rules.radius)
+                .iter()
+                .map(|x| grid.symbols()[*x].clone())
+                .collect();
+            NumberReport {
+                value: number.value,
+                row: number.row,
+                columns: number.columns.clone(),
+                symbols,
             }
         })
         .collect();
-    part_numbers
+    let gears = gear_reports(grid, rules)?;
+    ratio_total(&gears)?;
+    Ok(SchematicReport { numbers, gears })
+}
+
+/// Every gear symbol with the parts within the rules' radius, and its ratio when the rules
+/// count it
+fn gear_reports(
+    grid: &SchematicGrid,
+    rules: &SchematicRules,
+) -> Result<Vec<GearReport>, SchematicError> {
+    grid.symbols()
+        .iter()
+        .filter(|symbol| symbol.symbol == rules.gear)
+        .map(|symbol| {
+            let parts = grid.numbers_near(symbol.row, symbol.column, rules.radius);
+            let counted = rules.gear_parts.accepts(parts.len());
+            let ratio = if counted {
+                parts
+                    .iter()
+                    .try_fold(1u64, |ratio, x| {
+                        ratio.checked_mul(grid.numbers()[*x].value as u64)
+                    })
+                    .ok_or_else(|| SchematicError {
+                        message: format!(
+                            "the ratio of the gear at {}:{} overflows a u64",
+                            symbol.row, symbol.column
+                        ),
+                    })?
+            } else {
+                0
+            };
+            Ok(GearReport {
+                row: symbol.row,
+                column: symbol.column,
+                parts,
+                counted,
+                ratio,
+            })
+        })
+        .collect()
+}
+
+fn ratio_total(gears: &[GearReport]) -> Result<u64, SchematicError> {
+    gears.iter().try_fold(0u64, |total, gear| {
+        total.checked_add(gear.ratio).ok_or_else(|| SchematicError {
+            message: "the gear ratio total overflows a u64".to_string(),
+        })
+    })
+}
+
+/// A number placed on the grid. Its id is its index in `SchematicGrid::numbers`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GridNumber {
+    pub value: u32,
+    pub row: usize,
+    pub columns: Range<usize>,
+}
+
+/// What occupies a cell of the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    Number(usize),
+    Symbol(usize),
+}
+
+/// The whole schematic indexed by cell, so anything near a position can be looked up
+/// without rescanning the lines. Numbers and symbols are numbered in reading order.
+/// Distances count king moves, so a radius of 1 is the puzzle's adjacency.
+#[derive(Debug, Clone)]
+pub struct SchematicGrid {
+    numbers: Vec<GridNumber>,
+    symbols: Vec<SymbolReport>,
+    /// `cells[row][column]`, rows can have different lengths
+    cells: Vec<Vec<Cell>>,
+}
+
+impl SchematicGrid {
+    /// Errors when a number does not fit in a u32
+    pub fn new(
+        lines: &mut dyn Iterator<Item = String>,
+        rules: &SchematicRules,
+    ) -> Result<SchematicGrid, SchematicError> {
+        let mut grid = SchematicGrid {
+            numbers: Vec::new(),
+            symbols: Vec::new(),
+            cells: Vec::new(),
+        };
+        for (row, line) in lines.enumerate() {
+            let mut cells = vec![Cell::Empty; line.len()];
+            let scanned = scan_line(&line, rules).map_err(|err| SchematicError {
+                message: format!("row {}: {}", row, err),
+            })?;
+            scanned.numbers.into_iter().for_each(|number| {
+                number
+                    .index
+                    .clone()
+                    .for_each(|column| cells[column] = Cell::Number(grid.numbers.len()));
+                grid.numbers.push(GridNumber {
+                    value: number.value,
+                    row,
+                    columns: number.index,
+                });
+            });
+            scanned.symbols.into_iter().for_each(|symbol| {
+                cells[symbol.index] = Cell::Symbol(grid.symbols.len());
+                grid.symbols.push(SymbolReport {
+                    symbol: symbol.symbol,
+                    row,
+                    column: symbol.index,
+                });
+            });
+            grid.cells.push(cells);
+        }
+        Ok(grid)
+    }
+
+    pub fn numbers(&self) -> &[GridNumber] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[SymbolReport] {
+        &self.symbols
+    }
+
+    /// Id of the number covering the cell
+    pub fn number_at(&self, row: usize, column: usize) -> Option<usize> {
+        match self.cells.get(row)?.get(column)? {
+            Cell::Number(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Ids of the numbers with a digit within `radius` of the cell, in reading order
+    pub fn numbers_near(&self, row: usize, column: usize, radius: usize) -> Vec<usize> {
+        self.ids_in_area(
+            row..row + 1,
+            column..column + 1,
+            radius,
+            |cell| match cell {
+                Cell::Number(id) => Some(id),
+                _ => None,
+            },
+        )
+    }
+
+    /// Ids of the symbols within `radius` of any digit of the number, in reading order
+    pub fn symbols_near_number(&self, id: usize, radius: usize) -> Vec<usize> {
+        let number = &self.numbers[id];
+        self.ids_in_area(
+            number.row..number.row + 1,
+            number.columns.clone(),
+            radius,
+            |cell| match cell {
+                Cell::Symbol(id) => Some(id),
+                _ => None,
+            },
+        )
+    }
+
+    /// Ids of the numbers within `radius` of any symbol, in reading order
+    pub fn numbers_near_symbols(&self, radius: usize) -> Vec<usize> {
+        let mut ids: Vec<usize> = self
+            .symbols
+            .iter()
+            .flat_map(|x| self.numbers_near(x.row, x.column, radius))
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Collects the distinct ids found in the rows and columns grown by `radius` on every side
+    fn ids_in_area<F>(
+        &self,
+        rows: Range<usize>,
+        columns: Range<usize>,
+        radius: usize,
+        id_of: F,
+    ) -> Vec<usize>
+    where
+        F: Fn(Cell) -> Option<usize>,
+    {
+        let first_row = rows.start.saturating_sub(radius);
+        let last_row = rows.end.saturating_add(radius).min(self.cells.len());
+        let mut ids: Vec<usize> = (first_row..last_row)
+            .flat_map(|row| {
+                let cells = &self.cells[row];
+                let first_column = columns.start.saturating_sub(radius).min(cells.len());
+                let last_column = columns.end.saturating_add(radius).min(cells.len());
+                cells[first_column..last_column]
+                    .iter()
+                    .filter_map(|cell| id_of(*cell))
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderStyle {
+    /// Colours the cells with ANSI escape codes
+    Ansi,
+    /// Adds a line of markers under each row that has something to show
+    Plain,
+}
+
+/// What a cell of the schematic is highlighted as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    Part,
+    Number,
+    Gear,
+}
+
+impl Highlight {
+    fn ansi(&self) -> &'static str {
+        match self {
+            Highlight::Part => "\x1b[32m",
+            Highlight::Number => "\x1b[31m",
+            Highlight::Gear => "\x1b[1;33m",
+        }
+    }
+
+    fn marker(&self) -> char {
+        match self {
+            Highlight::Part => 'P',
+            Highlight::Number => 'n',
+            Highlight::Gear => 'G',
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// The rules for the render, which count a gear with exactly two parts by default
+fn render_rules(options: &[String]) -> Result<SchematicRules, RulesError> {
+    let mut rules = SchematicRules::from_options(options)?;
+    if options::flag_value(options, "--gear-parts").is_none() {
+        rules.gear_parts = GearParts::Exactly(2);
+    }
+    Ok(rules)
+}
+
+/// Draws the schematic with the report's findings highlighted, starting with a legend.
+/// Gears are highlighted when they counted under the rules, which the legend spells out.
+fn render_schematic(
+    lines: &[String],
+    report: &SchematicReport,
+    rules: &SchematicRules,
+    style: RenderStyle,
+) -> Vec<String> {
+    // Highlight of every byte column, row by row
+    let mut cells: Vec<Vec<Option<Highlight>>> =
+        lines.iter().map(|x| vec![None; x.len()]).collect();
+    report.numbers.iter().for_each(|number| {
+        let highlight = if number.is_part() {
+            Highlight::Part
+        } else {
+            Highlight::Number
+        };
+        number
+            .columns
+            .clone()
+            .for_each(|column| cells[number.row][column] = Some(highlight));
+    });
+    report
+        .gears
+        .iter()
+        .filter(|gear| gear.counted)
+        .for_each(|gear| cells[gear.row][gear.column] = Some(Highlight::Gear));
+
+    let gear = format!("'{}' with {} parts", rules.gear, rules.gear_parts);
+    let mut output = vec![match style {
+        RenderStyle::Ansi => format!(
+            "{}part{} {}not a part{} {}gear{} {}",
+            Highlight::Part.ansi(),
+            ANSI_RESET,
+            Highlight::Number.ansi(),
+            ANSI_RESET,
+            Highlight::Gear.ansi(),
+            ANSI_RESET,
+            gear
+        ),
+        RenderStyle::Plain => format!("P part, n not a part, G gear {}", gear),
+    }];
+    lines
+        .iter()
+        .zip(cells.iter())
+        .for_each(|(line, row)| match style {
+            RenderStyle::Ansi => output.push(render_ansi_row(line, row)),
+            RenderStyle::Plain => {
+                output.push(line.clone());
+                let markers: String = line
+                    .char_indices()
+                    .map(|(idx, _)| row[idx].map_or(' ', |x| x.marker()))
+                    .collect();
+                if !markers.trim().is_empty() {
+                    output.push(markers.trim_end().to_string());
+                }
+            }
+        });
+    output
+}
+
+/// Wraps each run of cells with the same highlight in its colour
+fn render_ansi_row(line: &str, row: &[Option<Highlight>]) -> String {
+    let mut rendered = String::with_capacity(line.len() * 2);
+    let mut current: Option<Highlight> = None;
+    line.char_indices().for_each(|(idx, c)| {
+        if row[idx] != current {
+            if current.is_some() {
+                rendered.push_str(ANSI_RESET);
+            }
+            if let Some(highlight) = row[idx] {
+                rendered.push_str(highlight.ansi());
+            }
+            current = row[idx];
+        }
+        rendered.push(c);
+    });
+    if current.is_some() {
+        rendered.push_str(ANSI_RESET);
+    }
+    rendered
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sample() -> Vec<String> {
+        "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598.."
+            .split('\n')
+            .map(|x| x.to_string())
+            .collect()
+    }
+
     #[test]
     fn test_day03_part1() {
         let lines: Vec<&str> = "467..114..
@@ -269,8 +914,11 @@ mod tests {
             .split('\n')
             .collect();
 
-        let result = day03_part1_handler(&mut lines.iter().map(|x| x.to_string()));
-        assert_eq!(4361, result);
+        let result = day03_part1_handler(
+            &mut lines.iter().map(|x| x.to_string()),
+            &SchematicRules::default(),
+        );
+        assert_eq!(Ok(4361), result);
     }
 
     #[test]
@@ -287,192 +935,514 @@ mod tests {
 .664.598.."
             .split('\n')
             .collect();
-        let result = day03_part2_handler(&mut lines.iter().map(|x| x.to_string()));
-        assert_eq!(467835, result);
+        let result = day03_part2_handler(
+            &mut lines.iter().map(|x| x.to_string()),
+            &SchematicRules::default(),
+        );
+        assert_eq!(Ok(467835), result);
     }
 
     #[test]
-    fn test_scan_area() {
-        let mut scan_area = ScanArea {
-            prev: None,
-            current: None,
-            next: None,
-        };
+    fn test_day03_part2_large_ratios() {
+        let lines = vec![
+            "999.999".to_string(),
+            "...*...".to_string(),
+            "999.999".to_string(),
+        ];
+        let rules = SchematicRules::from_options(&options(&["--gear-parts", "4"])).unwrap();
+        assert_eq!(
+            Ok(996005996001),
+            day03_part2_handler(&mut lines.into_iter(), &rules)
+        );
 
-        {
-            scan_area = scan_area.shift_new(Some("lineA".to_string()));
-            let expected = ScanArea {
-                prev: None,
-                current: None,
-                next: Some("lineA".to_string()),
-            };
-            assert_eq!(expected, scan_area);
-        }
+        // Three parts of u32::MAX do not fit in a u64
+        let lines = vec![
+            "4294967295*4294967295".to_string(),
+            ".........4294967295..".to_string(),
+        ];
+        assert_eq!(
+            "the ratio of the gear at 0:10 overflows a u64",
+            day03_part2_handler(&mut lines.into_iter(), &SchematicRules::default())
+                .unwrap_err()
+                .to_string()
+        );
+    }
 
-        {
-            scan_area = scan_area.shift_new(Some("lineB".to_string()));
-            let expected = ScanArea {
-                prev: None,
-                current: Some("lineA".to_string()),
-                next: Some("lineB".to_string()),
-            };
-            assert_eq!(expected, scan_area);
-        }
+    #[test]
+    fn test_numbers_past_u32() {
+        let lines = vec!["....".to_string(), "99999999999*1".to_string()];
+        let error = "row 1: the number 99999999999 at column 0 does not fit in a u32";
+        let rules = SchematicRules::default();
+        assert_eq!(
+            error,
+            day03_part1_handler(&mut lines.clone().into_iter(), &rules)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            error,
+            day03_part2_handler(&mut lines.clone().into_iter(), &rules)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(schematic_report(&mut lines.into_iter(), &rules).is_err());
+    }
 
-        scan_area = scan_area.shift_new(Some("lineC".to_string()));
-        let expected = ScanArea {
-            prev: Some("lineA".to_string()),
-            current: Some("lineB".to_string()),
-            next: Some("lineC".to_string()),
+    #[test]
+    fn test_day03_part1_neighbours() {
+        let total = |lines: &[&str]| {
+            day03_part1_handler(
+                &mut lines.iter().map(|x| x.to_string()),
+                &SchematicRules::default(),
+            )
+            .unwrap()
         };
-        assert_eq!(expected, scan_area);
+        assert_eq!(123, total(&["..123..", ".....*."]));
+        assert_eq!(123, total(&[".*.....", "..123.."]));
+        assert_eq!(0, total(&["..123..", "......."]));
+        assert_eq!(0, total(&["*......", "...123."]));
+        assert_eq!(
+            222 + 333 + 444 + 555,
+            total(&[
+                "..999.*..................",
+                "..111..222.*333.444*.555.",
+                "..999...................+",
+            ])
+        );
+    }
 
-        scan_area = scan_area.shift_new(None);
-        let expected = ScanArea {
-            prev: Some("lineB".to_string()),
-            current: Some("lineC".to_string()),
-            next: None,
-        };
-        assert_eq!(expected, scan_area);
+    #[test]
+    fn test_radius_applies_to_every_part() {
+        let lines = vec![
+            "1....".to_string(),
+            "..*..".to_string(),
+            "....2".to_string(),
+        ];
+        let rules = SchematicRules::from_options(&options(&["--radius", "2"])).unwrap();
+        assert_eq!(
+            Ok(0),
+            day03_part1_handler(&mut lines.clone().into_iter(), &SchematicRules::default())
+        );
+        assert_eq!(
+            Ok(3),
+            day03_part1_handler(&mut lines.clone().into_iter(), &rules)
+        );
+        assert_eq!(
+            Ok(2),
+            day03_part2_handler(&mut lines.clone().into_iter(), &rules)
+        );
+        assert_eq!(
+            3,
+            schematic_report(&mut lines.into_iter(), &rules)
+                .unwrap()
+                .part_total()
+        );
+        assert!(SchematicRules::from_options(&options(&["--radius", "-1"])).is_err());
+    }
 
-        scan_area = scan_area.shift_new(None);
-        let expected = ScanArea {
-            prev: Some("lineC".to_string()),
-            current: None,
-            next: None,
-        };
-        assert_eq!(expected, scan_area);
+    fn spans(input: &str) -> Vec<(Range<usize>, u32)> {
+        scan_line(input, &SchematicRules::default())
+            .unwrap()
+            .numbers
+            .into_iter()
+            .map(|x| (x.index, x.value))
+            .collect()
+    }
 
-        scan_area = scan_area.shift_new(None);
-        let expected = ScanArea {
-            prev: None,
-            current: None,
-            next: None,
-        };
-        assert_eq!(expected, scan_area);
+    #[test]
+    fn test_scan_line_numbers() {
+        assert!(scan_line("!@#$%^&*()-+=.", &SchematicRules::default())
+            .unwrap()
+            .numbers
+            .is_empty());
+        assert_eq!(
+            5,
+            scan_line("123..1..456..654..789", &SchematicRules::default())
+                .unwrap()
+                .numbers
+                .len()
+        );
+        // A number that is also a prefix or suffix of an earlier one keeps its own span
+        assert_eq!(
+            vec![(0..3, 123), (5..7, 12), (8..10, 23), (11..14, 123)],
+            spans("123..12.23*123")
+        );
+        assert_eq!(vec![(0..1, 7), (2..3, 7)], spans("7a7"));
+        assert_eq!(vec![(1..11, u32::MAX)], spans(".4294967295."));
+        assert_eq!(
+            "the number 4294967296 at column 1 does not fit in a u32",
+            scan_line(".4294967296.", &SchematicRules::default())
+                .unwrap_err()
+                .to_string()
+        );
     }
 
     #[test]
-    fn test_scan_area_handler() {
-        let mut scan_area = ScanArea {
-            prev: None,
-            current: Some("..123..".to_string()),
-            next: Some(".....*.".to_string()),
-        };
-        assert_eq!(123, scan_area_handler(scan_area));
+    fn test_scan_line_symbols() {
+        assert!(scan_line(".0123456789abcXYZ", &SchematicRules::default())
+            .unwrap()
+            .symbols
+            .is_empty());
+        assert_eq!(
+            13,
+            scan_line("!@#$%^&*()-+=", &SchematicRules::default())
+                .unwrap()
+                .symbols
+                .len()
+        );
+        let symbols: Vec<(char, usize)> = scan_line("1*2é3#", &SchematicRules::default())
+            .unwrap()
+            .symbols
+            .iter()
+            .map(|x| (x.symbol, x.index))
+            .collect();
+        assert_eq!(vec![('*', 1), ('é', 3), ('#', 6)], symbols);
+    }
 
-        scan_area = ScanArea {
-            prev: Some(".*.....".to_string()),
-            current: Some("..123..".to_string()),
-            next: None,
-        };
-        assert_eq!(123, scan_area_handler(scan_area));
+    #[test]
+    fn test_tokenizer_order() {
+        let tokens: Vec<String> = Tokenizer::new("..35*..633#", &SchematicRules::default())
+            .map(|token| match token.unwrap() {
+                Token::Number(x) => format!("{}@{:?}", x.value, x.index),
+                Token::Symbol(x) => format!("{}@{}", x.symbol, x.index),
+            })
+            .collect();
+        assert_eq!(vec!["35@2..4", "*@4", "633@7..10", "#@10"], tokens);
+    }
 
-        scan_area = ScanArea {
-            prev: None,
-            current: Some("..123..".to_string()),
-            next: Some(".......".to_string()),
-        };
-        assert_eq!(0, scan_area_handler(scan_area));
+    #[test]
+    fn test_scan_line_long() {
+        // Many repeats of the same number used to be found with a whole-line search each
+        let line = "123.".repeat(50_000);
+        let numbers = scan_line(&line, &SchematicRules::default())
+            .unwrap()
+            .numbers;
+        assert_eq!(50_000, numbers.len());
+        assert_eq!(
+            Range {
+                start: 199_996,
+                end: 199_999
+            },
+            numbers[49_999].index
+        );
+    }
 
-        {
-            scan_area = ScanArea {
-                prev: Some(".......".to_string()),
-                current: Some("..123..".to_string()),
-                next: None,
-            };
-            assert_eq!(0, scan_area_handler(scan_area));
-        }
+    #[test]
+    fn test_schematic_report() {
+        let report =
+            schematic_report(&mut sample().into_iter(), &SchematicRules::default()).unwrap();
+        assert_eq!(10, report.numbers.len());
+        assert_eq!(4361, report.part_total());
+        assert_eq!(467835, report.gear_ratio_total());
+        assert_eq!(
+            NumberReport {
+                value: 617,
+                row: 4,
+                columns: 0..3,
+                symbols: vec![SymbolReport {
+                    symbol: '*',
+                    row: 4,
+                    column: 3
+                }],
+            },
+            report.numbers[4]
+        );
+        let uncounted: Vec<u32> = report
+            .numbers
+            .iter()
+            .filter(|x| !x.is_part())
+            .map(|x| x.value)
+            .collect();
+        assert_eq!(vec![114, 58], uncounted);
 
-        let scan_area = ScanArea {
-            prev: Some("..999.*..................".to_string()),
-            current: Some("..111..222.*333.444*.555.".to_string()),
-            next: Some("..999...................+".to_string()),
-        };
-        assert_eq!(222 + 333 + 444 + 555, scan_area_handler(scan_area));
+        // The '*' next to 617 only has one part so it is not a gear
+        assert_eq!(3, report.gears.len());
+        assert_eq!(
+            GearReport {
+                row: 4,
+                column: 3,
+                parts: vec![4],
+                counted: false,
+                ratio: 0
+            },
+            report.gears[1]
+        );
+        let ratios: Vec<u64> = report.gears.iter().map(|x| x.ratio).collect();
+        assert_eq!(vec![16345, 0, 451490], ratios);
     }
 
     #[test]
-    fn test_part_number_location_extend_range() {
-        let part = PartNumberLocation {
-            index: Range { start: 0, end: 2 },
-            value: 13,
-        };
-        assert_eq!(Range { start: 0, end: 3 }, part.extended_range());
+    fn test_schematic_report_output() {
+        let lines = vec!["12.3".to_string(), ".*..".to_string(), "4...".to_string()];
+        let report = schematic_report(&mut lines.into_iter(), &SchematicRules::default()).unwrap();
+        assert_eq!(
+            vec![
+                "Part 12 at 0:0..2 touches '*' at 1:1",
+                "Number 3 at 0:3..4 touches nothing",
+                "Part 4 at 2:0..1 touches '*' at 1:1",
+                "Gear at 1:1 parts [12, 4] ratio 48",
+                "Part total 16, gear ratio total 48",
+            ],
+            report.to_text()
+        );
+        assert_eq!(
+            concat!(
+                r#"{"numbers":["#,
+                r#"{"value":12,"row":0,"start":0,"end":2,"part":true,"symbols":[{"symbol":"*","row":1,"column":1}]},"#,
+                r#"{"value":3,"row":0,"start":3,"end":4,"part":false,"symbols":[]},"#,
+                r#"{"value":4,"row":2,"start":0,"end":1,"part":true,"symbols":[{"symbol":"*","row":1,"column":1}]}],"#,
+                r#""gears":[{"row":1,"column":1,"parts":["#,
+                r#"{"value":12,"row":0,"start":0,"end":2},{"value":4,"row":2,"start":0,"end":1}],"counted":true,"ratio":48}],"#,
+                r#""part_total":16,"gear_ratio_total":48}"#
+            ),
+            report.to_json().to_string()
+        );
+    }
 
-        let part = PartNumberLocation {
-            index: Range { start: 10, end: 15 },
-            value: 1234,
-        };
-        assert_eq!(Range { start: 9, end: 16 }, part.extended_range());
+    #[test]
+    fn test_render_plain() {
+        let lines = sample();
+        let report =
+            schematic_report(&mut lines.iter().cloned(), &SchematicRules::default()).unwrap();
+        let rendered = render_schematic(
+            &lines,
+            &report,
+            &SchematicRules::default(),
+            RenderStyle::Plain,
+        );
+        assert_eq!(
+            vec![
+                "P part, n not a part, G gear '*' with 2+ parts",
+                "467..114..",
+                "PPP  nnn",
+                "...*......",
+                "   G",
+                "..35..633.",
+                "  PP  PPP",
+                "......#...",
+                "617*......",
+                "PPP",
+                ".....+.58.",
+                "       nn",
+                "..592.....",
+                "  PPP",
+                "......755.",
+                "      PPP",
+                "...$.*....",
+                "     G",
+                ".664.598..",
+                " PPP PPP",
+            ],
+            rendered
+        );
     }
 
     #[test]
-    fn test_part_intersects_gears_1() {
-        let part_number = PartNumberLocation {
-            index: Range { start: 0, end: 3 },
-            value: 123,
-        };
-        let gear = GearLocation {
-            index: 0,
-            _symbol: '*',
-        };
-        // should intersect
-        let gears = vec![gear];
-        assert!(part_intersects_gears(&part_number, &mut gears.iter()));
+    fn test_render_ansi() {
+        let lines = vec!["12*3".to_string(), "4...".to_string()];
+        let report =
+            schematic_report(&mut lines.iter().cloned(), &SchematicRules::default()).unwrap();
+        let rendered = render_schematic(
+            &lines,
+            &report,
+            &SchematicRules::default(),
+            RenderStyle::Ansi,
+        );
+        assert_eq!(3, rendered.len());
+        assert_eq!(
+            "\x1b[32m12\x1b[0m\x1b[1;33m*\x1b[0m\x1b[32m3\x1b[0m",
+            rendered[1]
+        );
+        // 4 touches nothing, the '*' is two columns away
+        assert_eq!("\x1b[31m4\x1b[0m...", rendered[2]);
     }
 
     #[test]
-    fn test_part_intersects_gears_2() {
-        let part_number = PartNumberLocation {
-            index: Range { start: 0, end: 3 },
-            value: 123,
+    fn test_render_follows_gear_rule() {
+        // A '*' with three parts is only highlighted when --gear-parts allows more than 2
+        let lines = vec!["2.3".to_string(), ".*.".to_string(), "4..".to_string()];
+        let render = |args: &[&str]| {
+            let rules = render_rules(&options(args)).unwrap();
+            let report = schematic_report(&mut lines.iter().cloned(), &rules).unwrap();
+            render_schematic(&lines, &report, &rules, RenderStyle::Plain)
         };
-        let gear = GearLocation {
-            index: 3,
-            _symbol: '+',
-        };
-        // should intersect
-        let gears = vec![gear];
-        assert!(part_intersects_gears(&part_number, &mut gears.iter()));
+        let rendered = render(&[]);
+        assert_eq!("P part, n not a part, G gear '*' with 2 parts", rendered[0]);
+        assert_eq!(vec![".*.", "4..", "P"], rendered[3..].to_vec());
+        let rendered = render(&["--gear-parts", "2+"]);
+        assert_eq!(
+            "P part, n not a part, G gear '*' with 2+ parts",
+            rendered[0]
+        );
+        assert_eq!(" G", rendered[4]);
+
+        // Two parts are highlighted under the default rule
+        let lines = vec!["2.3".to_string(), ".*.".to_string()];
+        let rules = render_rules(&[]).unwrap();
+        let report = schematic_report(&mut lines.iter().cloned(), &rules).unwrap();
+        let rendered = render_schematic(&lines, &report, &rules, RenderStyle::Plain);
+        assert_eq!(" G", rendered[4]);
+    }
+
+    fn options(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
     }
 
     #[test]
-    fn test_part_intersects_gears_3() {
-        let part_number = PartNumberLocation {
-            index: Range { start: 5, end: 8 },
-            value: 123,
-        };
-        // should intersect
-        let gears = vec![
-            GearLocation {
-                index: 3,
-                _symbol: '+',
-            },
-            GearLocation {
-                index: 8,
-                _symbol: '*',
-            },
-            GearLocation {
-                index: 11,
-                _symbol: '#',
-            },
+    fn test_rules_from_options() {
+        assert_eq!(
+            Ok(SchematicRules::default()),
+            SchematicRules::from_options(&[])
+        );
+        let rules = SchematicRules::from_options(&options(&[
+            "--symbols",
+            "@#X",
+            "--gear=@",
+            "--gear-parts",
+            "3",
+        ]))
+        .unwrap();
+        assert_eq!(SymbolSet::Only(vec!['@', '#', 'X']), rules.symbols);
+        assert_eq!('@', rules.gear);
+        assert_eq!(GearParts::Exactly(3), rules.gear_parts);
+        let rules = SchematicRules::from_options(&options(&["--gear-parts", "1+"])).unwrap();
+        assert_eq!(GearParts::AtLeast(1), rules.gear_parts);
+
+        let errors = [
+            vec!["--symbols", "*1"],
+            vec!["--gear", "**"],
+            vec!["--gear-parts", "0"],
+            vec!["--gear-parts", "two"],
+            vec!["--symbols", "#", "--gear", "*"],
+            vec!["--gear", "a"],
         ];
-        assert!(part_intersects_gears(&part_number, &mut gears.iter()));
+        errors.iter().for_each(|args| {
+            assert!(
+                SchematicRules::from_options(&options(args)).is_err(),
+                "{:?}",
+                args
+            );
+        });
+
+        // Without an explicit --gear the symbols may leave out the default gear, which only
+        // matters to part 2
+        let rules = SchematicRules::from_options(&options(&["--symbols", "#+"])).unwrap();
+        assert_eq!(SymbolSet::Only(vec!['#', '+']), rules.symbols);
+        assert_eq!(
+            "the gear '*' is not one of the symbols, add it to --symbols or pick another --gear",
+            rules.check_gear().unwrap_err().to_string()
+        );
+        assert_eq!(Ok(()), SchematicRules::default().check_gear());
     }
 
     #[test]
-    fn test_extract_part_number_locations() {
-        assert!(extract_part_number_locations("!@#$%^&*()-+=.").is_empty());
+    fn test_custom_symbols() {
+        // Only '#' and '*' are symbols, so '$' and '+' no longer make parts
+        let rules = SchematicRules::from_options(&options(&["--symbols", "#*"])).unwrap();
+        let total = day03_part1_handler(&mut sample().into_iter(), &rules);
+        assert_eq!(Ok(4361 - 664 - 592), total);
+
+        // Letters can be symbols too
+        let rules =
+            SchematicRules::from_options(&options(&["--symbols", "X", "--gear", "X"])).unwrap();
+        let lines = vec!["12X3".to_string(), "....".to_string()];
         assert_eq!(
-            5,
-            extract_part_number_locations("123..1..456..654..789").len()
+            Ok(15),
+            day03_part1_handler(&mut lines.clone().into_iter(), &rules)
+        );
+        assert_eq!(Ok(36), day03_part2_handler(&mut lines.into_iter(), &rules));
+    }
+
+    #[test]
+    fn test_gear_parts() {
+        let lines = vec!["2.3".to_string(), ".*.".to_string(), "4..".to_string()];
+        let total = |args: &[&str]| {
+            let rules = SchematicRules::from_options(&options(args)).unwrap();
+            day03_part2_handler(&mut lines.clone().into_iter(), &rules).unwrap()
+        };
+        assert_eq!(24, total(&[]));
+        assert_eq!(0, total(&["--gear-parts", "2"]));
+        assert_eq!(24, total(&["--gear-parts", "3"]));
+        assert_eq!(0, total(&["--gear-parts", "4+"]));
+
+        let rules = SchematicRules::from_options(&options(&["--gear-parts", "2"])).unwrap();
+        let report = schematic_report(&mut lines.clone().into_iter(), &rules).unwrap();
+        assert!(!report.gears[0].counted);
+        assert_eq!(3, report.gears[0].parts.len());
+    }
+
+    #[test]
+    fn test_grid_lookup() {
+        let grid =
+            SchematicGrid::new(&mut sample().into_iter(), &SchematicRules::default()).unwrap();
+        assert_eq!(10, grid.numbers().len());
+        assert_eq!(6, grid.symbols().len());
+        assert_eq!(Some(0), grid.number_at(0, 2));
+        assert_eq!(None, grid.number_at(0, 3));
+        assert_eq!(Some(9), grid.number_at(9, 7));
+        assert_eq!(None, grid.number_at(10, 0));
+        assert_eq!(None, grid.number_at(0, 10));
+        // 467 and 35 touch the first '*' from above and below
+        assert_eq!(vec![0, 2], grid.numbers_near(1, 3, 1));
+        assert_eq!(vec![0], grid.symbols_near_number(0, 1));
+        assert!(grid.symbols_near_number(1, 1).is_empty());
+    }
+
+    #[test]
+    fn test_grid_radius() {
+        let grid =
+            SchematicGrid::new(&mut sample().into_iter(), &SchematicRules::default()).unwrap();
+        assert!(grid.numbers_near(1, 3, 0).is_empty());
+        // 114 is two columns from the first '*'
+        assert_eq!(vec![0, 1, 2], grid.numbers_near(1, 3, 2));
+        assert_eq!(vec![0, 2, 3, 4, 6, 7, 8, 9], grid.numbers_near_symbols(1));
+        assert_eq!(10, grid.numbers_near_symbols(3).len());
+        assert_eq!(
+            grid.numbers().len(),
+            grid.numbers_near(0, 0, usize::MAX).len()
+        );
+    }
+
+    #[test]
+    fn test_report_within_radius() {
+        let lines = vec![
+            "1....".to_string(),
+            "..*..".to_string(),
+            ".....".to_string(),
+            "....22".to_string(),
+        ];
+        let mut rules = SchematicRules::default();
+        let grid = SchematicGrid::new(&mut lines.clone().into_iter(), &rules).unwrap();
+        let report = grid_report(&grid, &rules).unwrap();
+        assert_eq!(0, report.part_total());
+        rules.radius = 2;
+        let report = grid_report(&grid, &rules).unwrap();
+        assert_eq!(23, report.part_total());
+        assert_eq!(22, report.gear_ratio_total());
+        // The part handlers agree with the report
+        let rules = SchematicRules::default();
+        let report = schematic_report(&mut sample().into_iter(), &rules).unwrap();
+        assert_eq!(
+            Ok(report.part_total()),
+            day03_part1_handler(&mut sample().into_iter(), &rules)
+        );
+        assert_eq!(
+            Ok(report.gear_ratio_total()),
+            day03_part2_handler(&mut sample().into_iter(), &rules)
         );
     }
 
     #[test]
-    fn test_extract_gears() {
-        assert!(extract_gears(".0123456789").is_empty());
-        assert_eq!(13, extract_gears("!@#$%^&*()-+=").len());
+    fn test_report_ratio_overflow() {
+        let lines = vec![
+            "4294967295*4294967295".to_string(),
+            ".........4294967295..".to_string(),
+        ];
+        assert_eq!(
+            "the ratio of the gear at 0:10 overflows a u64",
+            schematic_report(&mut lines.into_iter(), &SchematicRules::default())
+                .unwrap_err()
+                .to_string()
+        );
     }
 }
@@ -1,31 +1,28 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt,
-    ops::Range,
-    str::FromStr,
-};
+use std::{collections::HashSet, fmt, ops::Range, str::FromStr};
 
+use super::bitset::BitSet;
 use super::parsers::parse_str_with_separator;
 
 /// Pretty print the result of the calculations
-pub fn day04_part1(lines: &mut dyn Iterator<Item = String>) {
+pub fn day04_part1(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
     let total = day04_part1_handler(lines);
     println!("Total: {}", total);
+    Ok(())
 }
 
 /// 1. Parse the lines into Cards that have two lists of values
-/// 2. Create a set from each list. Find the Intersection of each set
+/// 2. Count the intersection of the two sets of numbers
 /// 3. Return 2^<Number of Sets>
 fn day04_part1_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
     let base: u32 = 2;
     let score = lines
         .flat_map(|x| Card::from_str(x.as_str()))
-        .map(|x: Card| x.matches())
+        .map(|x: Card| x.match_count())
         .map(|x| {
-            if x.is_empty() {
+            if x == 0 {
                 0
             } else {
-                base.pow((x.len() - 1).try_into().unwrap())
+                base.pow((x - 1).try_into().unwrap())
             }
         })
         .sum();
@@ -33,66 +30,130 @@ fn day04_part1_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
 }
 
 /// Pretty print the result of the calculations
-pub fn day04_part2(lines: &mut dyn Iterator<Item = String>) {
-    let total = day04_part2_handler(lines);
+pub fn day04_part2(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let total = day04_part2_handler(lines).map_err(|x| x.to_string())?;
     println!("Total: {}", total);
+    Ok(())
 }
 
 /// 1. Parse the lines into Cards that have two lists of values
 /// 2. Evaluate the card and see how many cards of the next type you 'win'
-///     2a. Create a set from each list. Find the Intersection of each set
-///     2b. Aggregate the number of copies of a card. Create an entry in the Hashmap if it doesn't exist, add 1
-/// 3. Emit number of copies of a card
-///     3b. Remove copies from the aggregation as the work is completed. There is a bow wave of future data to be processed.
-/// 4. Sum the total
-fn day04_part2_handler(lines: &mut dyn Iterator<Item = String>) -> u32 {
-    let mut card_copies: HashMap<u32, u32> = HashMap::new();
-    let score = lines
+///    2a. Count the intersection of the two sets of numbers
+///    2b. Every copy of this card wins one copy of each bonus card, so add this card's copies to theirs
+/// 3. Sum the copies of every card
+///
+/// Cards are indexed by their position in the input, so each card is visited once no matter
+/// how many copies it has.
+fn day04_part2_handler(lines: &mut dyn Iterator<Item = String>) -> Result<u64, CardCountError> {
+    let matches: Vec<usize> = lines
         .flat_map(|x| Card::from_str(x.as_str()))
-        .map(|x: Card| {
-            let matches = x.matches();
-            let bonus_card_ids = Range {
-                start: x.id + 1,
-                end: x.id + 1 + u32::try_from(matches.len()).unwrap(),
-            };
-
-            // Add in the current card count
-            let copies_of_this_card = *card_copies.entry(x.id).or_insert(0) + 1;
-
-            // Repeat this section for the number of copies of this card
-            Range {
-                start: 0,
-                end: copies_of_this_card,
-            }
-            .for_each(|_| {
-                bonus_card_ids.clone().for_each(|key| {
-                    *card_copies.entry(key).or_insert(0) += 1;
-                })
-            });
-            // Remove the current and cleanup the map
-            card_copies.remove(&x.id);
-
-            copies_of_this_card
+        .map(|x: Card| x.match_count())
+        .collect();
+
+    // Every card starts with the original
+    let mut copies: Vec<u64> = vec![1; matches.len()];
+    for (position, matched) in matches.iter().enumerate() {
+        let bonus_cards = Range {
+            start: position + 1,
+            end: (position + 1 + matched).min(copies.len()),
+        };
+        for bonus in bonus_cards {
+            copies[bonus] =
+                copies[bonus]
+                    .checked_add(copies[position])
+                    .ok_or_else(|| CardCountError {
+                        message: format!("copies of card {} overflow a u64", bonus + 1),
+                    })?;
+        }
+    }
+
+    copies.iter().try_fold(0u64, |acc, x| {
+        acc.checked_add(*x).ok_or_else(|| CardCountError {
+            message: "the total number of cards overflows a u64".to_string(),
         })
-        .sum();
-    score
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CardCountError {
+    message: String,
+}
+
+impl fmt::Display for CardCountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Number of words in a card bitset, enough for the values below 128
+const CARD_WORDS: usize = 2;
+
+/// One side of a card. Stored as a bitset when every value fits, hashed otherwise
+#[derive(Eq, PartialEq, Debug)]
+enum CardNumbers {
+    Bits(BitSet<CARD_WORDS>),
+    Hashed(HashSet<u32>),
+}
+
+impl CardNumbers {
+    fn contains(&self, value: u32) -> bool {
+        match self {
+            CardNumbers::Bits(bits) => bits.contains(value as usize),
+            CardNumbers::Hashed(set) => set.contains(&value),
+        }
+    }
+
+    fn values(&self) -> Vec<u32> {
+        match self {
+            CardNumbers::Bits(bits) => bits.iter().map(|x| x as u32).collect(),
+            CardNumbers::Hashed(set) => set.iter().copied().collect(),
+        }
+    }
+}
+
+impl From<Vec<u32>> for CardNumbers {
+    fn from(values: Vec<u32>) -> CardNumbers {
+        match BitSet::from_values(values.iter().map(|x| *x as usize)) {
+            Some(bits) => CardNumbers::Bits(bits),
+            None => CardNumbers::Hashed(HashSet::from_iter(values)),
+        }
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
 struct Card {
     id: u32,
-    winners: Vec<u32>,
-    numbers: Vec<u32>,
+    winners: CardNumbers,
+    numbers: CardNumbers,
 }
 
 impl Card {
     fn matches(&self) -> Vec<u32> {
-        let winners_set: HashSet<u32> = HashSet::from_iter(self.winners.iter().cloned());
-        let numbers_set: HashSet<u32> = HashSet::from_iter(self.numbers.iter().cloned());
-        let intersection: Vec<u32> = winners_set.intersection(&numbers_set).copied().collect();
-        intersection
+        match (&self.winners, &self.numbers) {
+            (CardNumbers::Bits(winners), CardNumbers::Bits(numbers)) => winners
+                .intersection(numbers)
+                .iter()
+                .map(|x| x as u32)
+                .collect(),
+            (winners, numbers) => winners
+                .values()
+                .into_iter()
+                .filter(|x| numbers.contains(*x))
+                .collect(),
+        }
+    }
+
+    /// Number of winners on the card, without collecting them
+    fn match_count(&self) -> usize {
+        match (&self.winners, &self.numbers) {
+            (CardNumbers::Bits(winners), CardNumbers::Bits(numbers)) => {
+                winners.intersection_len(numbers)
+            }
+            _ => self.matches().len(),
+        }
     }
 }
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct ParseCardError {
     message: String,
@@ -136,13 +197,13 @@ impl FromStr for Card {
             message: "Failed to split numbers from numbers segment".to_string(),
         })?;
 
-        let winners = parse_str_with_separator(lhs, " ");
-        let numbers = parse_str_with_separator(rhs, " ");
+        let winners: Vec<u32> = parse_str_with_separator(lhs, " ");
+        let numbers: Vec<u32> = parse_str_with_separator(rhs, " ");
 
         let card = Card {
             id: card_id,
-            winners,
-            numbers,
+            winners: CardNumbers::from(winners),
+            numbers: CardNumbers::from(numbers),
         };
         Ok(card)
     }
@@ -151,6 +212,7 @@ impl FromStr for Card {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::xorshift::XorShift;
 
     #[test]
     fn test_day04_part1() {
@@ -179,19 +241,137 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
             .collect();
 
         let result = day04_part2_handler(&mut lines.iter().map(|x| x.to_string()));
-        assert_eq!(30, result);
+        assert_eq!(Ok(30), result);
+    }
+
+    /// Cards that each match the same `matches` numbers
+    fn cascading_cards(count: usize, matches: usize) -> Vec<String> {
+        let numbers: Vec<String> = (1..=matches).map(|x| x.to_string()).collect();
+        let numbers = numbers.join(" ");
+        (1..=count)
+            .map(|id| format!("Card {}: {} | {}", id, numbers, numbers))
+            .collect()
+    }
+
+    #[test]
+    fn test_day04_part2_large_counts() {
+        // Card n has 2^(n-1) copies while every card wins all the remaining ones
+        let lines = cascading_cards(40, 40);
+        assert_eq!(
+            Ok((1u64 << 40) - 1),
+            day04_part2_handler(&mut lines.into_iter())
+        );
+
+        // Wins past the last card are ignored
+        let lines = cascading_cards(3, 10);
+        assert_eq!(Ok(1 + 2 + 4), day04_part2_handler(&mut lines.into_iter()));
+    }
+
+    #[test]
+    fn test_day04_part2_overflow() {
+        let lines = cascading_cards(70, 70);
+        assert_eq!(
+            "copies of card 65 overflow a u64",
+            day04_part2_handler(&mut lines.into_iter())
+                .unwrap_err()
+                .to_string()
+        );
+
+        // Cards 64 and 65 have 2^63 and 2^64 - 1 copies, so each fits but the total does not
+        let lines = cascading_cards(65, 63);
+        assert_eq!(
+            "the total number of cards overflows a u64",
+            day04_part2_handler(&mut lines.into_iter())
+                .unwrap_err()
+                .to_string()
+        );
     }
 
     #[test]
     fn test_matches() {
         let card = Card {
             id: 0,
-            winners: vec![0, 1, 2, 3, 7, 8, 9],
-            numbers: vec![0, 3, 7, 9, 10, 11, 12],
+            winners: CardNumbers::from(vec![0, 1, 2, 3, 7, 8, 9]),
+            numbers: CardNumbers::from(vec![0, 3, 7, 9, 10, 11, 12]),
         };
         let calculated: HashSet<u32> = HashSet::from_iter(card.matches().iter().cloned());
-        let expected: HashSet<u32> = HashSet::from_iter(vec![0, 3, 7, 9].iter().cloned());
+        let expected: HashSet<u32> = HashSet::from_iter([0, 3, 7, 9].iter().cloned());
         assert_eq!(expected, calculated);
+        assert_eq!(4, card.match_count());
+    }
+
+    #[test]
+    fn test_matches_large_values() {
+        // Numbers past the bitset width fall back to hashing, on one side or both
+        let card = Card::from_str("Card 1: 5 127 128 1000 | 1000 5 6 128").unwrap();
+        assert!(matches!(card.winners, CardNumbers::Hashed(_)));
+        let mut calculated = card.matches();
+        calculated.sort();
+        assert_eq!(vec![5, 128, 1000], calculated);
+        assert_eq!(3, card.match_count());
+
+        let card = Card::from_str("Card 2: 5 127 3 | 1000 5 127").unwrap();
+        assert!(matches!(card.winners, CardNumbers::Bits(_)));
+        assert!(matches!(card.numbers, CardNumbers::Hashed(_)));
+        assert_eq!(vec![5, 127], card.matches());
+        assert_eq!(2, card.match_count());
+    }
+
+    /// Synthetic cards in the puzzle format with 10 winners and 25 numbers below 100
+    fn synthetic_cards(count: usize) -> String {
+        let mut random = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut next = move || random.below(100);
+        let mut text = String::new();
+        for id in 1..=count {
+            let winners: Vec<String> = (0..10).map(|_| format!("{:>2}", next())).collect();
+            let numbers: Vec<String> = (0..25).map(|_| format!("{:>2}", next())).collect();
+            text.push_str(&format!(
+                "Card {:>7}: {} | {}\n",
+                id,
+                winners.join(" "),
+                numbers.join(" ")
+            ));
+        }
+        text
+    }
+
+    /// Compares hash set intersections against the bitsets on a million synthetic cards.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_day04_matches`
+    #[test]
+    #[ignore]
+    fn bench_day04_matches() {
+        let path = std::env::temp_dir().join("day04_bench_cards.txt");
+        std::fs::write(&path, synthetic_cards(1_000_000)).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let start = std::time::Instant::now();
+        let hashed: usize = text
+            .lines()
+            .map(|line| {
+                let (_, numbers) = line.split_once(':').unwrap();
+                let (lhs, rhs) = numbers.split_once('|').unwrap();
+                let winners: HashSet<u32> =
+                    parse_str_with_separator(lhs, " ").into_iter().collect();
+                let numbers: HashSet<u32> =
+                    parse_str_with_separator(rhs, " ").into_iter().collect();
+                winners.intersection(&numbers).count()
+            })
+            .sum();
+        let hashed_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let bits: usize = text
+            .lines()
+            .map(|line| Card::from_str(line).unwrap().match_count())
+            .sum();
+        let bits_elapsed = start.elapsed();
+
+        assert_eq!(hashed, bits);
+        println!(
+            "1,000,000 cards: hash sets {:?}, bitsets {:?}",
+            hashed_elapsed, bits_elapsed
+        );
     }
 
     #[test]
@@ -199,8 +379,8 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"
         let calculated = Card::from_str("Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11");
         let expected = Card {
             id: 6,
-            winners: vec![31, 18, 13, 56, 72],
-            numbers: vec![74, 77, 10, 23, 35, 67, 36, 11],
+            winners: CardNumbers::from(vec![31, 18, 13, 56, 72]),
+            numbers: CardNumbers::from(vec![74, 77, 10, 23, 35, 67, 36, 11]),
         };
         assert_eq!(expected, calculated?);
         Ok(())
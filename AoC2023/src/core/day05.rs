@@ -1,92 +1,365 @@
-use std::{ops::Range, str::FromStr};
+use std::{fmt, ops::Range, str::FromStr};
+
+use super::graph::bfs;
+use crate::io::options;
 
 /// Pretty print the result of the calculations
-pub fn day05_part1(lines: &mut dyn Iterator<Item = String>) {
-    let total = day05_part1_handler(lines);
+pub fn day05_part1(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let total = day05_part1_handler(lines).map_err(|x| x.to_string())?;
     println!("Total: {}", total);
+    Ok(())
 }
 
 /// Procedure
-/// 1. Parse the inputs into structs for the mappings and a list for the seeds.
-/// 2. Iterate over the seeds to find the location
-///     2a. Build the path from seed to location
-/// 3. Select the smallest location
-fn day05_part1_handler(lines: &mut dyn Iterator<Item = String>) -> u64 {
-    let scenario = Scenario::from_str_itr(lines, Scenario::part1_seed_extractor);
-    let min_location = scenario
-        .seeds
-        .iter()
-        .map(|seed| location_for_seed(*seed, &scenario))
-        .min();
-    min_location.expect("There should have been an answer")
+/// 1. Parse the inputs into an almanac of category maps and a list for the seeds.
+/// 2. Resolve the chain of maps from seed to location
+/// 3. Compose the chain into a single mapping
+/// 4. Iterate over the seeds to find the location
+/// 5. Select the smallest location
+fn day05_part1_handler(lines: &mut dyn Iterator<Item = String>) -> Result<u64, AlmanacError> {
+    let almanac = Almanac::from_str_itr(lines, Almanac::part1_seed_extractor)?;
+    let layers = almanac.path("seed", "location")?;
+    let mapping = PiecewiseMapping::compose_layers(&layers);
+    let min_location = almanac.seeds.iter().map(|seed| mapping.apply(*seed)).min();
+    min_location.ok_or_else(no_seeds_error)
 }
 
 /// Pretty print the result of the calculations
-pub fn day05_part2(lines: &mut dyn Iterator<Item = String>) {
-    let total = day05_part2_handler(lines);
+pub fn day05_part2(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let total = day05_part2_handler(lines).map_err(|x| x.to_string())?;
     println!("Total: {}", total);
+    Ok(())
 }
 
 /// Procedure
-/// 1. Parse the inputs into structs for the mappings and a list for the seeds.
-/// 2. Iterate over the seeds to find the location
-///     2a. Build the path from seed to location
-/// 3. Select the smallest location
-fn day05_part2_handler(lines: &mut dyn Iterator<Item = String>) -> u64 {
-    let scenario = Scenario::from_str_itr(lines, Scenario::part1_seed_extractor);
-    let min_location = scenario
+/// 1. Parse the inputs into an almanac of category maps and pair up the seeds into ranges.
+/// 2. Resolve the chain of maps from seed to location
+/// 3. Push the seed ranges through each layer of mappings
+///    3a. Split each range where it crosses the boundary of a mapping
+/// 4. Select the smallest start of the location ranges
+fn day05_part2_handler(lines: &mut dyn Iterator<Item = String>) -> Result<u64, AlmanacError> {
+    let almanac = Almanac::from_str_itr(lines, Almanac::part1_seed_extractor)?;
+    let layers = almanac.path("seed", "location")?;
+    let seeds = almanac.seed_ranges()?;
+    let min_location = locations_for_seed_ranges(seeds, &layers)
+        .iter()
+        .map(|rng| rng.start as u64)
+        .min();
+    min_location.ok_or_else(no_seeds_error)
+}
+
+/// Prints the path of every seed to its location and the mapping applied at each step
+pub fn day05_part1_trace(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let almanac =
+        Almanac::from_str_itr(lines, Almanac::part1_seed_extractor).map_err(|x| x.to_string())?;
+    let layers = almanac
+        .path("seed", "location")
+        .map_err(|x| x.to_string())?;
+    let min_location = almanac
         .seeds
         .iter()
-        .map(|seed| location_for_seed(*seed, &scenario))
+        .map(|seed| {
+            let steps = trace_seed(*seed, &layers);
+            print!("{}", format_seed_trace(*seed, &steps));
+            steps.last().map_or(*seed, |x| x.value)
+        })
         .min();
-    min_location.expect("There should have been an answer")
+    let total = min_location.ok_or_else(|| no_seeds_error().to_string())?;
+    println!("Total: {}", total);
+    Ok(())
+}
+
+/// Prints the path of every seed range to its location ranges and the mapping applied to
+/// each piece at each step
+pub fn day05_part2_trace(lines: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let almanac =
+        Almanac::from_str_itr(lines, Almanac::part1_seed_extractor).map_err(|x| x.to_string())?;
+    let layers = almanac
+        .path("seed", "location")
+        .map_err(|x| x.to_string())?;
+    let seed_ranges = almanac.seed_ranges().map_err(|x| x.to_string())?;
+    let min_location = seed_ranges
+        .iter()
+        .filter_map(|seeds| {
+            let steps = trace_seed_range(seeds.clone(), &layers);
+            print!("{}", format_seed_range_trace(seeds, &steps));
+            steps
+                .last()
+                .and_then(|(_, pieces)| pieces.iter().map(|(rng, _)| rng.start as u64).min())
+        })
+        .min();
+    let total = min_location.ok_or_else(|| no_seeds_error().to_string())?;
+    println!("Total: {}", total);
+    Ok(())
+}
+
+/// The error for an almanac without any seeds to find the smallest location of
+fn no_seeds_error() -> AlmanacError {
+    AlmanacError {
+        message: "There are no seeds to find a location for".to_string(),
+    }
 }
 
-fn destination_for_source(source: u64, rngs: &[RangeMapping]) -> u64 {
-    let destination: Vec<u64> = rngs
+/// Prints the seeds that lead to each location given with `--location`, which takes one
+/// location or a comma separated list of them
+pub fn day05_seeds_for_location(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<(), String> {
+    let report = day05_seeds_for_location_handler(lines, options).map_err(|x| x.to_string())?;
+    report.iter().for_each(|x| println!("{}", x));
+    Ok(())
+}
+
+/// Procedure
+/// 1. Parse the locations to look up and the almanac
+/// 2. Compose the chain of maps from seed to location into a single mapping
+/// 3. Invert the mapping when it is one to one, so each query is a single binary search
+/// 4. Otherwise fall back to collecting every preimage of each location
+fn day05_seeds_for_location_handler(
+    lines: &mut dyn Iterator<Item = String>,
+    options: &[String],
+) -> Result<Vec<String>, AlmanacError> {
+    let locations = locations_from_options(options)?;
+    let almanac = Almanac::from_str_itr(lines, Almanac::part1_seed_extractor)?;
+    let layers = almanac.path("seed", "location")?;
+    let mapping = PiecewiseMapping::compose_layers(&layers);
+    let inverse = mapping.inverse();
+    let report = locations
         .iter()
-        .flat_map(|rng| rng.find_destination(source))
+        .map(|location| {
+            let seeds = match &inverse {
+                Some(inverse) => vec![inverse.apply(*location)],
+                None => mapping.preimage(*location),
+            };
+            format_location_seeds(*location, &seeds)
+        })
         .collect();
-    if destination.is_empty() {
-        source
-    } else {
-        destination[0]
+    Ok(report)
+}
+
+fn locations_from_options(options: &[String]) -> Result<Vec<u64>, AlmanacError> {
+    let value = options::flag_value(options, "--location").ok_or(AlmanacError {
+        message: "Expected --location L, or a comma separated list of locations".to_string(),
+    })?;
+    value
+        .split(',')
+        .map(|x| {
+            x.trim().parse::<u64>().map_err(|_| AlmanacError {
+                message: format!("'{}' is not a location", x),
+            })
+        })
+        .collect()
+}
+
+fn format_location_seeds(location: u64, seeds: &[u64]) -> String {
+    if seeds.is_empty() {
+        return format!("location {}: no seeds", location);
     }
+    let seeds: Vec<String> = seeds.iter().map(|x| x.to_string()).collect();
+    format!("location {}: seeds {}", location, seeds.join(", "))
 }
 
-fn location_for_seed(seed: u64, scenario: &Scenario) -> u64 {
-    let soil = destination_for_source(seed, &scenario.seed_to_soil_mappings);
-    let fertilizer = destination_for_source(soil, &scenario.soil_to_fertilizer_mappings);
-    let water = destination_for_source(fertilizer, &scenario.fertilizer_to_waters);
-    let light = destination_for_source(water, &scenario.water_to_lights);
-    let temperature = destination_for_source(light, &scenario.light_to_temperatures);
-    let humidity = destination_for_source(temperature, &scenario.temperature_to_humidities);
-    destination_for_source(humidity, &scenario.humidity_to_locations)
+/// The value a seed reached in one category and the entry that moved it there
+#[derive(Debug, PartialEq, Eq)]
+struct TraceStep<'a> {
+    category: &'a str,
+    value: u64,
+    mapping: Option<&'a RangeMapping>,
 }
 
-struct Scenario {
+fn trace_seed<'a>(seed: u64, layers: &[&'a CategoryMap]) -> Vec<TraceStep<'a>> {
+    let mut value = seed;
+    layers
+        .iter()
+        .map(|layer| {
+            let mapping = layer.table.lookup(value);
+            value = mapping
+                .and_then(|rng| rng.find_destination(value))
+                .unwrap_or(value);
+            TraceStep {
+                category: layer.destination.as_str(),
+                value,
+                mapping,
+            }
+        })
+        .collect()
+}
+
+/// Each category with the ranges the seeds reached there and the entry that moved each piece
+type RangeTraceStep<'a> = (&'a str, Vec<(Range<u128>, Option<&'a RangeMapping>)>);
+
+fn trace_seed_range<'a>(seeds: Range<u128>, layers: &[&'a CategoryMap]) -> Vec<RangeTraceStep<'a>> {
+    let mut sources = vec![seeds];
+    layers
+        .iter()
+        .map(|layer| {
+            let pieces = trace_ranges_for_source(sources.clone(), &layer.table);
+            sources = pieces.iter().map(|(rng, _)| rng.clone()).collect();
+            (layer.destination.as_str(), pieces)
+        })
+        .collect()
+}
+
+fn format_mapping(mapping: Option<&RangeMapping>) -> String {
+    match mapping {
+        Some(rng) => format!("via {}", rng),
+        None => "unmapped".to_string(),
+    }
+}
+
+fn format_seed_trace(seed: u64, steps: &[TraceStep]) -> String {
+    let mut output = format!("seed {}\n", seed);
+    steps.iter().for_each(|step| {
+        output.push_str(&format!(
+            "  {} {} {}\n",
+            step.category,
+            step.value,
+            format_mapping(step.mapping)
+        ));
+    });
+    output
+}
+
+fn format_seed_range_trace(seeds: &Range<u128>, steps: &[RangeTraceStep]) -> String {
+    let mut output = format!("seeds {}..{}\n", seeds.start, seeds.end);
+    steps.iter().for_each(|(category, pieces)| {
+        pieces.iter().for_each(|(rng, mapping)| {
+            output.push_str(&format!(
+                "  {} {}..{} {}\n",
+                category,
+                rng.start,
+                rng.end,
+                format_mapping(*mapping)
+            ));
+        });
+    });
+    output
+}
+
+/// Maps every source range through the layer. The parts of a range that no mapping
+/// covers pass through unchanged.
+fn destination_ranges_for_source(
+    sources: Vec<Range<u128>>,
+    table: &MappingTable,
+) -> Vec<Range<u128>> {
+    trace_ranges_for_source(sources, table)
+        .into_iter()
+        .map(|(rng, _)| rng)
+        .collect()
+}
+
+/// Like `destination_ranges_for_source` but also returns the entry that moved each piece
+fn trace_ranges_for_source(
+    sources: Vec<Range<u128>>,
+    table: &MappingTable,
+) -> Vec<(Range<u128>, Option<&RangeMapping>)> {
+    let mut unmapped = sources;
+    let mut mapped: Vec<(Range<u128>, Option<&RangeMapping>)> = Vec::new();
+    table.entries().iter().for_each(|rng| {
+        let mut remainder: Vec<Range<u128>> = Vec::new();
+        unmapped.iter().for_each(|source| {
+            let (destination, leftovers) = rng.map_range(source);
+            mapped.extend(destination.map(|x| (x, Some(rng))));
+            remainder.extend(leftovers);
+        });
+        unmapped = remainder;
+    });
+    mapped.extend(unmapped.into_iter().map(|x| (x, None)));
+    mapped
+}
+
+fn locations_for_seed_ranges(seeds: Vec<Range<u128>>, layers: &[&CategoryMap]) -> Vec<Range<u128>> {
+    layers.iter().fold(seeds, |sources, layer| {
+        destination_ranges_for_source(sources, &layer.table)
+    })
+}
+
+/// One 'X-to-Y map:' section of the almanac
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CategoryMap {
+    source: String,
+    destination: String,
+    table: MappingTable,
+}
+
+impl CategoryMap {
+    /// Parses a header like 'seed-to-soil map:' into an empty map
+    fn from_header(header: &str) -> Result<CategoryMap, AlmanacError> {
+        let categories =
+            header
+                .trim()
+                .strip_suffix("map:")
+                .map(|x| x.trim())
+                .ok_or(AlmanacError {
+                    message: format!("'{}' is not a map header", header),
+                })?;
+        let mut splits = categories.split("-to-");
+        match (splits.next(), splits.next(), splits.next()) {
+            (Some(source), Some(destination), None)
+                if !source.is_empty() && !destination.is_empty() =>
+            {
+                Ok(CategoryMap {
+                    source: source.to_string(),
+                    destination: destination.to_string(),
+                    table: MappingTable::default(),
+                })
+            }
+            _ => Err(AlmanacError {
+                message: format!("'{}' should name a source and destination", header),
+            }),
+        }
+    }
+}
+
+/// The seeds and every category map, linked by category name
+struct Almanac {
     seeds: Vec<u64>,
-    seed_to_soil_mappings: Vec<RangeMapping>,
-    soil_to_fertilizer_mappings: Vec<RangeMapping>,
-    fertilizer_to_waters: Vec<RangeMapping>,
-    water_to_lights: Vec<RangeMapping>,
-    light_to_temperatures: Vec<RangeMapping>,
-    temperature_to_humidities: Vec<RangeMapping>,
-    humidity_to_locations: Vec<RangeMapping>,
-}
-
-impl Scenario {
-    fn new() -> Scenario {
-        Scenario {
-            seeds: Vec::new(),
-            seed_to_soil_mappings: Vec::new(),
-            soil_to_fertilizer_mappings: Vec::new(),
-            fertilizer_to_waters: Vec::new(),
-            water_to_lights: Vec::new(),
-            light_to_temperatures: Vec::new(),
-            temperature_to_humidities: Vec::new(),
-            humidity_to_locations: Vec::new(),
+    maps: Vec<CategoryMap>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct AlmanacError {
+    message: String,
+}
+
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Almanac {
+    /// Part 2 reads the seeds as pairs of 'start length'. Errors if a start has no length or
+    /// a range runs past u64::MAX. A pair of length 0 holds no seeds and is left out.
+    fn seed_ranges(&self) -> Result<Vec<Range<u128>>, AlmanacError> {
+        if !self.seeds.len().is_multiple_of(2) {
+            return Err(AlmanacError {
+                message: format!(
+                    "The seeds should be 'start length' pairs but there are {} numbers",
+                    self.seeds.len()
+                ),
+            });
         }
+        self.seeds
+            .chunks(2)
+            .filter(|pair| pair[1] > 0)
+            .map(|pair| {
+                let rng = Range {
+                    start: pair[0] as u128,
+                    end: pair[0] as u128 + pair[1] as u128,
+                };
+                if rng.end > DOMAIN_END {
+                    return Err(AlmanacError {
+                        message: format!(
+                            "The seeds {} {} run past the largest u64",
+                            pair[0], pair[1]
+                        ),
+                    });
+                }
+                Ok(rng)
+            })
+            .collect()
     }
 
     fn part1_seed_extractor(line: &str) -> Vec<u64> {
@@ -105,62 +378,93 @@ impl Scenario {
         parsed
     }
 
+    /// Reads the seeds line and then any number of 'X-to-Y map:' sections in any order.
+    /// Errors if a mapping appears outside of a section, the same link is given twice,
+    /// or the source ranges of a section overlap.
     fn from_str_itr(
         lines: &mut dyn Iterator<Item = String>,
         seed_extractor: fn(&str) -> Vec<u64>,
-    ) -> Scenario {
-        let mut s = Scenario::new();
+    ) -> Result<Almanac, AlmanacError> {
+        let mut seeds: Vec<u64> = Vec::new();
+        let mut maps: Vec<CategoryMap> = Vec::new();
+        let mut entries: Vec<Vec<RangeMapping>> = Vec::new();
+        let mut in_section = false;
 
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if x.contains("seeds:") {
-                s.seeds.append(&mut (seed_extractor)(x.as_str()));
-            }
-        });
-        lines.next(); // advance one.
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("seed-to-soil map:") {
-                s.seed_to_soil_mappings
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                in_section = false;
+            } else if line.starts_with("seeds:") {
+                seeds.append(&mut (seed_extractor)(line));
+            } else if line.ends_with("map:") {
+                let map = CategoryMap::from_header(line)?;
+                if maps
+                    .iter()
+                    .any(|x| x.source == map.source && x.destination == map.destination)
+                {
+                    return Err(AlmanacError {
+                        message: format!(
+                            "The {}-to-{} map is given more than once",
+                            map.source, map.destination
+                        ),
+                    });
+                }
+                maps.push(map);
+                entries.push(Vec::new());
+                in_section = true;
+            } else {
+                let current = entries
+                    .last_mut()
+                    .filter(|_| in_section)
+                    .ok_or(AlmanacError {
+                        message: format!("'{}' is not inside a map section", line),
+                    })?;
+                let mapping = RangeMapping::from_str(line).map_err(|err| AlmanacError {
+                    message: err.message,
+                })?;
+                current.push(mapping);
             }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("soil-to-fertilizer map:") {
-                s.soil_to_fertilizer_mappings
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("fertilizer-to-water map:") {
-                s.fertilizer_to_waters
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("water-to-light map:") {
-                s.water_to_lights
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("light-to-temperature map:") {
-                s.light_to_temperatures
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("temperature-to-humidity map:") {
-                s.temperature_to_humidities
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
-        lines.take_while(|x| !x.is_empty()).for_each(|x| {
-            if !x.contains("humidity-to-location map:") {
-                s.humidity_to_locations
-                    .push(RangeMapping::from_str(x.as_str()).unwrap());
-            }
-        });
+        }
+
+        let maps = maps
+            .into_iter()
+            .zip(entries)
+            .map(|(mut map, entries)| {
+                map.table = MappingTable::new(entries).map_err(|err| AlmanacError {
+                    message: format!("{}-to-{} map: {}", map.source, map.destination, err),
+                })?;
+                Ok(map)
+            })
+            .collect::<Result<Vec<CategoryMap>, AlmanacError>>()?;
+
+        Ok(Almanac { seeds, maps })
+    }
 
-        s
+    /// Returns the chain of maps that leads from the source category to the destination category
+    fn path(&self, source: &str, destination: &str) -> Result<Vec<&CategoryMap>, AlmanacError> {
+        let search = bfs(source, |category| {
+            self.maps
+                .iter()
+                .filter(|x| x.source == *category)
+                .map(|x| x.destination.as_str())
+                .collect::<Vec<&str>>()
+        });
+        let categories = search.path_to(&destination).ok_or(AlmanacError {
+            message: format!(
+                "There is no chain of maps from {} to {}",
+                source, destination
+            ),
+        })?;
+        let layers = categories
+            .windows(2)
+            .map(|pair| {
+                self.maps
+                    .iter()
+                    .find(|x| x.source == pair[0] && x.destination == pair[1])
+                    .expect("The search only follows existing maps")
+            })
+            .collect();
+        Ok(layers)
     }
 }
 
@@ -171,6 +475,20 @@ struct RangeMapping {
     range_len: u64,
 }
 
+impl fmt::Display for RangeMapping {
+    /// Shows the source and destination ranges, e.g. '98..100 -> 50..52'
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}..{} -> {}..{}",
+            self.src_range_start,
+            self.src_range_end(),
+            self.dst_range_start,
+            self.dst_range_start as u128 + self.range_len as u128
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct ParseRangeMappingError {
     message: String,
@@ -182,20 +500,19 @@ impl FromStr for RangeMapping {
     fn from_str(input: &str) -> Result<RangeMapping, Self::Err> {
         // Segment the line
         let mut splits = input.split(" ");
-        let dst_range_start = splits.next().and_then(|x| match x.trim().parse::<u64>() {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        });
-        let src_range_start = splits.next().and_then(|x| match x.trim().parse::<u64>() {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        });
-        let range_len = splits.next().and_then(|x| match x.trim().parse::<u64>() {
-            Ok(x) => Some(x),
-            Err(_) => None,
-        });
+        let dst_range_start = splits.next().and_then(|x| x.trim().parse::<u64>().ok());
+        let src_range_start = splits.next().and_then(|x| x.trim().parse::<u64>().ok());
+        let range_len = splits.next().and_then(|x| x.trim().parse::<u64>().ok());
 
         match (dst_range_start, src_range_start, range_len) {
+            (Some(dst), Some(src), Some(len))
+                if dst as u128 + len as u128 > DOMAIN_END
+                    || src as u128 + len as u128 > DOMAIN_END =>
+            {
+                Err(ParseRangeMappingError {
+                    message: format!("{} runs past the largest u64", input),
+                })
+            }
             (Some(dst), Some(src), Some(len)) => Ok(RangeMapping {
                 dst_range_start: dst,
                 src_range_start: src,
@@ -209,18 +526,284 @@ impl FromStr for RangeMapping {
 }
 
 impl RangeMapping {
+    /// Exclusive end of the source range. Kept as u128 so a range can end at u64::MAX.
+    fn src_range_end(&self) -> u128 {
+        self.src_range_start as u128 + self.range_len as u128
+    }
+
     /// If the input value is in source range, then return destination
     fn find_destination(&self, input: u64) -> Option<u64> {
-        let rng = Range {
-            start: self.src_range_start,
-            end: self.src_range_start + self.range_len,
+        // what is the difference from source to the start?
+        let delta = input
+            .checked_sub(self.src_range_start)
+            .filter(|x| *x < self.range_len)?;
+        self.dst_range_start.checked_add(delta)
+    }
+
+    /// Splits the input range into the part this mapping covers, already moved to the
+    /// destination, and the parts before and after it that are left unmapped.
+    /// Ranges are u128 so that they can end at u64::MAX.
+    fn map_range(&self, input: &Range<u128>) -> (Option<Range<u128>>, Vec<Range<u128>>) {
+        let src_range_start = self.src_range_start as u128;
+        let start = input.start.max(src_range_start);
+        let end = input.end.min(self.src_range_end());
+        if start >= end {
+            return (None, vec![input.clone()]);
+        }
+
+        let mut leftovers: Vec<Range<u128>> = Vec::new();
+        if input.start < start {
+            leftovers.push(Range {
+                start: input.start,
+                end: start,
+            });
+        }
+        if end < input.end {
+            leftovers.push(Range {
+                start: end,
+                end: input.end,
+            });
+        }
+        let destination = Range {
+            start: self.dst_range_start as u128 + (start - src_range_start),
+            end: self.dst_range_start as u128 + (end - src_range_start),
         };
-        if rng.contains(&input) {
-            // what is the difference from source to the start?
-            let delta = input - self.src_range_start;
-            return Some(self.dst_range_start + delta);
+        (Some(destination), leftovers)
+    }
+}
+
+/// The RangeMappings of one layer sorted by source start, with no two source ranges overlapping,
+/// so a lookup is a binary search.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct MappingTable {
+    entries: Vec<RangeMapping>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct MappingTableError {
+    message: String,
+}
+
+impl fmt::Display for MappingTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl MappingTable {
+    /// Sorts the entries and rejects them if any source ranges overlap. Empty ranges are dropped.
+    fn new(entries: Vec<RangeMapping>) -> Result<MappingTable, MappingTableError> {
+        let mut entries: Vec<RangeMapping> =
+            entries.into_iter().filter(|x| x.range_len > 0).collect();
+        entries.sort_by_key(|x| x.src_range_start);
+
+        let overlaps = MappingTable::overlaps(&entries);
+        if !overlaps.is_empty() {
+            let described: Vec<String> = overlaps
+                .iter()
+                .map(|(a, b)| {
+                    format!(
+                        "{}..{} overlaps {}..{}",
+                        a.src_range_start,
+                        a.src_range_end(),
+                        b.src_range_start,
+                        b.src_range_end()
+                    )
+                })
+                .collect();
+            return Err(MappingTableError {
+                message: format!("source ranges overlap: {}", described.join(", ")),
+            });
+        }
+        Ok(MappingTable { entries })
+    }
+
+    /// Reports each pair of entries whose source ranges overlap.
+    /// Expects the entries to be sorted by source start.
+    fn overlaps(entries: &[RangeMapping]) -> Vec<(RangeMapping, RangeMapping)> {
+        let mut overlaps = Vec::new();
+        // Entry with the furthest reaching end seen so far
+        let mut furthest: Option<&RangeMapping> = None;
+        entries.iter().for_each(|rng| {
+            if let Some(prev) = furthest {
+                if rng.src_range_start as u128 >= prev.src_range_end() {
+                    furthest = Some(rng);
+                    return;
+                }
+                overlaps.push((prev.clone(), rng.clone()));
+                if rng.src_range_end() <= prev.src_range_end() {
+                    return;
+                }
+            }
+            furthest = Some(rng);
+        });
+        overlaps
+    }
+
+    fn entries(&self) -> &[RangeMapping] {
+        &self.entries
+    }
+
+    /// Returns the entry whose source range holds the value
+    fn lookup(&self, source: u64) -> Option<&RangeMapping> {
+        let idx = self
+            .entries
+            .partition_point(|x| x.src_range_start <= source)
+            .checked_sub(1)?;
+        let rng = &self.entries[idx];
+        rng.find_destination(source).map(|_| rng)
+    }
+}
+
+/// One past u64::MAX, the exclusive end of every mapping's domain
+const DOMAIN_END: u128 = 1 << 64;
+
+/// A mapping over every u64 made of linear pieces. Piece `i` covers
+/// `starts[i]..starts[i + 1]` (the last one runs to u64::MAX) and adds `offsets[i]` to its input.
+/// Consecutive layers can be composed into one, so a seed finds its location with a single
+/// binary search instead of walking every layer.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct PiecewiseMapping {
+    starts: Vec<u64>,
+    offsets: Vec<i128>,
+}
+
+impl PiecewiseMapping {
+    /// Builds the mapping for a single layer. Values no RangeMapping covers map to themselves.
+    fn from_layer(table: &MappingTable) -> PiecewiseMapping {
+        let mut breakpoints: Vec<u128> = table
+            .entries()
+            .iter()
+            .flat_map(|rng| [rng.src_range_start as u128, rng.src_range_end()])
+            .filter(|x| *x < DOMAIN_END)
+            .collect();
+        breakpoints.push(0);
+        breakpoints.sort();
+        breakpoints.dedup();
+
+        let pieces = breakpoints.iter().map(|start| {
+            let start = *start as u64;
+            let offset = table.lookup(start).map_or(0, |rng| {
+                rng.dst_range_start as i128 - rng.src_range_start as i128
+            });
+            (start, offset)
+        });
+        PiecewiseMapping::from_pieces(pieces)
+    }
+
+    /// Collapses the layers into one mapping that takes the first layer's source to the
+    /// last layer's destination
+    fn compose_layers(layers: &[&CategoryMap]) -> PiecewiseMapping {
+        layers
+            .iter()
+            .map(|layer| PiecewiseMapping::from_layer(&layer.table))
+            .fold(PiecewiseMapping::identity(), |acc, x| acc.then(&x))
+    }
+
+    fn identity() -> PiecewiseMapping {
+        PiecewiseMapping {
+            starts: vec![0],
+            offsets: vec![0],
+        }
+    }
+
+    /// Builds from (start, offset) pairs sorted by start, merging neighbours that share an offset
+    fn from_pieces(pieces: impl Iterator<Item = (u64, i128)>) -> PiecewiseMapping {
+        let mut starts: Vec<u64> = Vec::new();
+        let mut offsets: Vec<i128> = Vec::new();
+        pieces.for_each(|(start, offset)| {
+            if offsets.last() != Some(&offset) {
+                starts.push(start);
+                offsets.push(offset);
+            }
+        });
+        PiecewiseMapping { starts, offsets }
+    }
+
+    /// The half open span of inputs covered by piece `idx`
+    fn piece(&self, idx: usize) -> (u128, u128) {
+        let end = self.starts.get(idx + 1).map_or(DOMAIN_END, |x| *x as u128);
+        (self.starts[idx] as u128, end)
+    }
+
+    fn apply(&self, input: u64) -> u64 {
+        let idx = self.starts.partition_point(|x| *x <= input) - 1;
+        (input as i128 + self.offsets[idx]) as u64
+    }
+
+    /// Returns the mapping that applies `self` and then `next`
+    fn then(&self, next: &PiecewiseMapping) -> PiecewiseMapping {
+        let mut pieces: Vec<(u64, i128)> = Vec::new();
+        (0..self.starts.len()).for_each(|idx| {
+            let (start, end) = self.piece(idx);
+            let offset = self.offsets[idx];
+            // Cut this piece wherever its image crosses a breakpoint of the next mapping
+            let image_start = (start as i128 + offset) as u64;
+            let image_end = end as i128 + offset;
+            let mut next_idx = next.starts.partition_point(|x| *x <= image_start) - 1;
+            let mut cut = start;
+            while cut < end {
+                pieces.push((cut as u64, offset + next.offsets[next_idx]));
+                next_idx += 1;
+                match next.starts.get(next_idx) {
+                    Some(x) if (*x as i128) < image_end => {
+                        cut = (*x as i128 - offset) as u128;
+                    }
+                    _ => break,
+                }
+            }
+        });
+        PiecewiseMapping::from_pieces(pieces.into_iter())
+    }
+
+    /// Every input that maps to the value, smallest first. A piecewise mapping is not
+    /// always one to one, so there may be none or several.
+    fn preimage(&self, value: u64) -> Vec<u64> {
+        (0..self.starts.len())
+            .filter_map(|idx| {
+                let (start, end) = self.piece(idx);
+                let candidate = value as i128 - self.offsets[idx];
+                if (start as i128) <= candidate && candidate < end as i128 {
+                    Some(candidate as u64)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The mapping from destination back to source, or None when two inputs share an output
+    /// or some output is never reached.
+    fn inverse(&self) -> Option<PiecewiseMapping> {
+        let mut images: Vec<(u128, u128, i128)> = (0..self.starts.len())
+            .map(|idx| {
+                let (start, end) = self.piece(idx);
+                let offset = self.offsets[idx];
+                (
+                    (start as i128 + offset) as u128,
+                    (end as i128 + offset) as u128,
+                    -offset,
+                )
+            })
+            .collect();
+        images.sort();
+
+        // The images must tile the whole domain with no gaps or overlaps
+        let mut expected_start = 0u128;
+        for (start, end, _) in images.iter() {
+            if *start != expected_start {
+                return None;
+            }
+            expected_start = *end;
+        }
+        if expected_start != DOMAIN_END {
+            return None;
         }
-        None
+        Some(PiecewiseMapping::from_pieces(
+            images
+                .into_iter()
+                .map(|(start, _, offset)| (start as u64, offset)),
+        ))
     }
 }
 
@@ -240,11 +823,254 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_range_mapping_from_str() -> Result<(), ParseRangeMappingError> {
-        let calculated = match RangeMapping::from_str("3154320624 3939365694 227285246") {
-            Ok(it) => it,
-            Err(err) => return Err(err),
+    fn test_range_mapping_map_range() {
+        let range_mapping = RangeMapping {
+            src_range_start: 10,
+            dst_range_start: 100,
+            range_len: 5,
         };
+        // Fully inside
+        assert_eq!(
+            (
+                Some(Range {
+                    start: 101,
+                    end: 103
+                }),
+                vec![]
+            ),
+            range_mapping.map_range(&Range { start: 11, end: 13 })
+        );
+        // Fully outside
+        assert_eq!(
+            (None, vec![Range { start: 15, end: 20 }]),
+            range_mapping.map_range(&Range { start: 15, end: 20 })
+        );
+        // Overhangs both ends
+        assert_eq!(
+            (
+                Some(Range {
+                    start: 100,
+                    end: 105
+                }),
+                vec![Range { start: 5, end: 10 }, Range { start: 15, end: 20 }]
+            ),
+            range_mapping.map_range(&Range { start: 5, end: 20 })
+        );
+        // Overlaps the end
+        assert_eq!(
+            (
+                Some(Range {
+                    start: 103,
+                    end: 105
+                }),
+                vec![Range { start: 15, end: 17 }]
+            ),
+            range_mapping.map_range(&Range { start: 13, end: 17 })
+        );
+    }
+
+    #[test]
+    fn test_destination_ranges_for_source() {
+        let table = MappingTable::new(vec![
+            RangeMapping {
+                dst_range_start: 50,
+                src_range_start: 98,
+                range_len: 2,
+            },
+            RangeMapping {
+                dst_range_start: 52,
+                src_range_start: 50,
+                range_len: 48,
+            },
+        ])
+        .unwrap();
+        let mut calculated = destination_ranges_for_source(
+            vec![Range {
+                start: 40,
+                end: 100,
+            }],
+            &table,
+        );
+        calculated.sort_by_key(|x| x.start);
+        assert_eq!(
+            vec![
+                Range { start: 40, end: 50 },
+                Range { start: 50, end: 52 },
+                Range {
+                    start: 52,
+                    end: 100
+                },
+            ],
+            calculated
+        );
+    }
+
+    #[test]
+    fn test_range_mapping_near_u64_max() {
+        let range_mapping = RangeMapping::from_str(&format!("0 {} 10", u64::MAX - 9)).unwrap();
+        assert_eq!(u64::MAX as u128 + 1, range_mapping.src_range_end());
+        assert_eq!(Some(9), range_mapping.find_destination(u64::MAX));
+        assert_eq!(None, range_mapping.find_destination(u64::MAX - 10));
+        let max = u64::MAX as u128;
+        assert_eq!(
+            (
+                Some(Range { start: 0, end: 4 }),
+                vec![Range {
+                    start: max - 20,
+                    end: max - 9
+                }]
+            ),
+            range_mapping.map_range(&Range {
+                start: max - 20,
+                end: max - 5
+            })
+        );
+
+        // A destination range that ends at u64::MAX
+        let range_mapping = RangeMapping::from_str(&format!("{} 0 10", u64::MAX - 9)).unwrap();
+        assert_eq!(Some(u64::MAX), range_mapping.find_destination(9));
+        assert_eq!(
+            (
+                Some(Range {
+                    start: max - 9,
+                    end: max + 1
+                }),
+                vec![]
+            ),
+            range_mapping.map_range(&Range { start: 0, end: 10 })
+        );
+        assert_eq!(
+            (
+                Some(Range {
+                    start: max - 4,
+                    end: max + 1
+                }),
+                vec![Range { start: 10, end: 12 }]
+            ),
+            range_mapping.map_range(&Range { start: 5, end: 12 })
+        );
+
+        // One past the end of the u64 values is rejected
+        assert!(RangeMapping::from_str(&format!("0 {} 11", u64::MAX - 9)).is_err());
+        assert!(RangeMapping::from_str(&format!("{} 0 11", u64::MAX - 9)).is_err());
+    }
+
+    /// Maps the value through the table with a binary search, values no entry covers map to
+    /// themselves
+    fn table_destination(table: &MappingTable, source: u64) -> u64 {
+        table
+            .lookup(source)
+            .and_then(|rng| rng.find_destination(source))
+            .unwrap_or(source)
+    }
+
+    #[test]
+    fn test_mapping_table_lookup() -> Result<(), MappingTableError> {
+        let table = MappingTable::new(vec![
+            RangeMapping::from_str("52 50 48").unwrap(),
+            RangeMapping::from_str("50 98 2").unwrap(),
+            RangeMapping::from_str("7 200 0").unwrap(),
+            RangeMapping::from_str(&format!("0 {} 1", u64::MAX)).unwrap(),
+        ])?;
+        // Sorted and the empty range dropped
+        let starts: Vec<u64> = table.entries().iter().map(|x| x.src_range_start).collect();
+        assert_eq!(vec![50, 98, u64::MAX], starts);
+
+        assert_eq!(None, table.lookup(49));
+        assert_eq!(Some(&table.entries()[0]), table.lookup(50));
+        assert_eq!(Some(&table.entries()[1]), table.lookup(99));
+        assert_eq!(None, table.lookup(100));
+        assert_eq!(49, table_destination(&table, 49));
+        assert_eq!(81, table_destination(&table, 79));
+        assert_eq!(51, table_destination(&table, 99));
+        assert_eq!(0, table_destination(&table, u64::MAX));
+        assert_eq!(u64::MAX - 1, table_destination(&table, u64::MAX - 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mapping_table_overlaps() {
+        let entries = vec![
+            RangeMapping::from_str("0 10 10").unwrap(),
+            RangeMapping::from_str("0 12 2").unwrap(),
+            RangeMapping::from_str("0 15 10").unwrap(),
+            RangeMapping::from_str("0 25 5").unwrap(),
+        ];
+        let overlaps = MappingTable::overlaps(&entries);
+        assert_eq!(2, overlaps.len());
+        assert_eq!((entries[0].clone(), entries[1].clone()), overlaps[0]);
+        assert_eq!((entries[0].clone(), entries[2].clone()), overlaps[1]);
+
+        let err = MappingTable::new(entries).unwrap_err();
+        assert_eq!(
+            "source ranges overlap: 10..20 overlaps 12..14, 10..20 overlaps 15..25",
+            err.message
+        );
+
+        // Touching ranges are fine
+        assert!(MappingTable::new(vec![
+            RangeMapping::from_str("0 10 10").unwrap(),
+            RangeMapping::from_str("0 20 10").unwrap(),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_almanac_overlapping_section() {
+        let mut lines = sample_data();
+        lines.insert(4, "0 60 5".to_string());
+        let result = Almanac::from_str_itr(&mut lines.into_iter(), Almanac::part1_seed_extractor);
+        assert!(result.is_err_and(|err| err.message.starts_with("seed-to-soil map:")));
+    }
+
+    /// Compares a linear scan against the binary search on a large table.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_mapping_table`
+    #[test]
+    #[ignore]
+    fn bench_mapping_table() {
+        let entries: Vec<RangeMapping> = (0..10_000u64)
+            .map(|x| RangeMapping {
+                dst_range_start: (x * 7_919) % 10_000 * 1_000,
+                src_range_start: x * 1_000,
+                range_len: 900,
+            })
+            .collect();
+        let table = MappingTable::new(entries).unwrap();
+        let inputs: Vec<u64> = (0..100_000u64).map(|x| x * 104_729 % 10_000_000).collect();
+
+        let start = std::time::Instant::now();
+        let linear: u64 = inputs
+            .iter()
+            .map(|x| {
+                table
+                    .entries()
+                    .iter()
+                    .find_map(|rng| rng.find_destination(*x))
+                    .unwrap_or(*x)
+            })
+            .fold(0, |acc, x| acc.wrapping_add(x));
+        let linear_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let binary: u64 = inputs
+            .iter()
+            .map(|x| table_destination(&table, *x))
+            .fold(0, |acc, x| acc.wrapping_add(x));
+        let binary_time = start.elapsed();
+
+        println!(
+            "{} lookups over {} entries: linear {:?}, binary search {:?}",
+            inputs.len(),
+            table.entries().len(),
+            linear_time,
+            binary_time
+        );
+        assert_eq!(linear, binary);
+    }
+
+    #[test]
+    fn test_parse_range_mapping_from_str() -> Result<(), ParseRangeMappingError> {
+        let calculated = RangeMapping::from_str("3154320624 3939365694 227285246")?;
         let expected = RangeMapping {
             dst_range_start: 3154320624,
             src_range_start: 3939365694,
@@ -294,17 +1120,377 @@ humidity-to-location map:
         lines
     }
 
+    #[test]
+    fn test_category_map_from_header() {
+        let expected = CategoryMap {
+            source: "seed".to_string(),
+            destination: "soil".to_string(),
+            table: MappingTable::default(),
+        };
+        assert_eq!(Ok(expected), CategoryMap::from_header("seed-to-soil map:"));
+        assert!(CategoryMap::from_header("seed-soil map:").is_err());
+        assert!(CategoryMap::from_header("-to-soil map:").is_err());
+        assert!(CategoryMap::from_header("seed-to-soil").is_err());
+    }
+
+    #[test]
+    fn test_almanac_sections_in_any_order() -> Result<(), AlmanacError> {
+        // Move the first section to the end and add an unrelated one
+        let mut lines = sample_data();
+        let seed_to_soil: Vec<String> = lines.drain(2..6).collect();
+        lines.push("".to_string());
+        lines.extend(seed_to_soil.into_iter().filter(|x| !x.is_empty()));
+        lines.push("".to_string());
+        lines.push("seed-to-colour map:".to_string());
+        lines.push("0 0 100".to_string());
+
+        let almanac = Almanac::from_str_itr(&mut lines.into_iter(), Almanac::part1_seed_extractor)?;
+        assert_eq!(8, almanac.maps.len());
+        let layers = almanac.path("seed", "location")?;
+        let names: Vec<&str> = layers.iter().map(|x| x.destination.as_str()).collect();
+        assert_eq!(
+            vec![
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity",
+                "location"
+            ],
+            names
+        );
+        assert_eq!(82, PiecewiseMapping::compose_layers(&layers).apply(79));
+
+        // Any two linked categories can be used
+        let layers = almanac.path("water", "temperature")?;
+        assert_eq!(2, layers.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_almanac_missing_link() -> Result<(), AlmanacError> {
+        let mut lines = sample_data();
+        let idx = lines
+            .iter()
+            .position(|x| x.starts_with("water-to-light"))
+            .unwrap();
+        lines.drain(idx..idx + 4);
+        let almanac = Almanac::from_str_itr(&mut lines.into_iter(), Almanac::part1_seed_extractor)?;
+        assert!(almanac.path("seed", "location").is_err());
+        assert!(almanac.path("location", "seed").is_err());
+        assert!(almanac.path("seed", "water").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_almanac_duplicate_link() {
+        let mut lines = sample_data();
+        lines.push("".to_string());
+        lines.push("seed-to-soil map:".to_string());
+        lines.push("1 2 3".to_string());
+        let result = Almanac::from_str_itr(&mut lines.into_iter(), Almanac::part1_seed_extractor);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_almanac_mapping_outside_section() {
+        let lines: Vec<String> = vec!["seeds: 1 2", "", "1 2 3"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect();
+        let result = Almanac::from_str_itr(&mut lines.into_iter(), Almanac::part1_seed_extractor);
+        assert!(result.is_err());
+    }
+
+    /// Walks the layers one value at a time, as a reference for the composed mapping
+    fn naive_location(seed: u64, layers: &[&CategoryMap]) -> u64 {
+        layers.iter().fold(seed, |source, layer| {
+            layer
+                .table
+                .entries()
+                .iter()
+                .find_map(|rng| rng.find_destination(source))
+                .unwrap_or(source)
+        })
+    }
+
+    fn sample_almanac() -> Almanac {
+        Almanac::from_str_itr(
+            &mut sample_data().into_iter(),
+            Almanac::part1_seed_extractor,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_piecewise_mapping_from_layer() {
+        let mapping = PiecewiseMapping::from_layer(
+            &MappingTable::new(vec![
+                RangeMapping::from_str("50 98 2").unwrap(),
+                RangeMapping::from_str("52 50 48").unwrap(),
+            ])
+            .unwrap(),
+        );
+        assert_eq!(vec![0, 50, 98, 100], mapping.starts);
+        assert_eq!(0, mapping.apply(0));
+        assert_eq!(49, mapping.apply(49));
+        assert_eq!(52, mapping.apply(50));
+        assert_eq!(81, mapping.apply(79));
+        assert_eq!(50, mapping.apply(98));
+        assert_eq!(51, mapping.apply(99));
+        assert_eq!(100, mapping.apply(100));
+        assert_eq!(u64::MAX, mapping.apply(u64::MAX));
+    }
+
+    #[test]
+    fn test_piecewise_mapping_compose() {
+        let almanac = sample_almanac();
+        let layers = almanac.path("seed", "location").unwrap();
+        let mapping = PiecewiseMapping::compose_layers(&layers);
+        assert_eq!(82, mapping.apply(79));
+        assert_eq!(43, mapping.apply(14));
+        assert_eq!(86, mapping.apply(55));
+        assert_eq!(35, mapping.apply(13));
+        (0..200).chain([u64::MAX - 1, u64::MAX]).for_each(|seed| {
+            assert_eq!(naive_location(seed, &layers), mapping.apply(seed));
+        });
+    }
+
+    #[test]
+    fn test_piecewise_mapping_preimage() {
+        let almanac = sample_almanac();
+        let layers = almanac.path("seed", "location").unwrap();
+        let mapping = PiecewiseMapping::compose_layers(&layers);
+        assert!(mapping.preimage(82).contains(&79));
+        (0..200).for_each(|location| {
+            let expected: Vec<u64> = (0..200)
+                .filter(|seed| mapping.apply(*seed) == location)
+                .collect();
+            let calculated: Vec<u64> = mapping
+                .preimage(location)
+                .into_iter()
+                .filter(|seed| *seed < 200)
+                .collect();
+            assert_eq!(expected, calculated);
+        });
+
+        // Both 2 and 12 land on 2
+        let mapping = PiecewiseMapping::from_layer(
+            &MappingTable::new(vec![RangeMapping::from_str("0 10 5").unwrap()]).unwrap(),
+        );
+        assert_eq!(vec![2, 12], mapping.preimage(2));
+        assert_eq!(Vec::<u64>::new(), mapping.preimage(12));
+    }
+
+    #[test]
+    fn test_piecewise_mapping_inverse() {
+        let almanac = sample_almanac();
+        let layers = almanac.path("seed", "soil").unwrap();
+        let mapping = PiecewiseMapping::compose_layers(&layers);
+        let inverse = mapping.inverse().unwrap();
+        assert_eq!(79, inverse.apply(81));
+        assert_eq!(98, inverse.apply(50));
+        (0..200).for_each(|seed| assert_eq!(seed, inverse.apply(mapping.apply(seed))));
+
+        let mapping = PiecewiseMapping::from_layer(
+            &MappingTable::new(vec![RangeMapping::from_str("0 10 5").unwrap()]).unwrap(),
+        );
+        assert_eq!(None, mapping.inverse());
+    }
+
+    #[test]
+    fn test_trace_seed() {
+        let almanac = sample_almanac();
+        let layers = almanac.path("seed", "location").unwrap();
+        let steps = trace_seed(79, &layers);
+        let values: Vec<(&str, u64)> = steps.iter().map(|x| (x.category, x.value)).collect();
+        assert_eq!(
+            vec![
+                ("soil", 81),
+                ("fertilizer", 81),
+                ("water", 81),
+                ("light", 74),
+                ("temperature", 78),
+                ("humidity", 78),
+                ("location", 82)
+            ],
+            values
+        );
+        assert_eq!(
+            Some(&RangeMapping::from_str("52 50 48").unwrap()),
+            steps[0].mapping
+        );
+        assert_eq!(None, steps[1].mapping);
+
+        assert_eq!(
+            "seed 79
+  soil 81 via 50..98 -> 52..100
+  fertilizer 81 unmapped
+  water 81 unmapped
+  light 74 via 25..95 -> 18..88
+  temperature 78 via 64..77 -> 68..81
+  humidity 78 unmapped
+  location 82 via 56..93 -> 60..97
+",
+            format_seed_trace(79, &steps)
+        );
+    }
+
+    #[test]
+    fn test_trace_seed_range() {
+        let almanac = sample_almanac();
+        let layers = almanac.path("seed", "location").unwrap();
+        let steps = trace_seed_range(Range { start: 55, end: 68 }, &layers);
+        assert_eq!(7, steps.len());
+        assert_eq!("soil", steps[0].0);
+        assert_eq!(
+            vec![(
+                Range { start: 57, end: 70 },
+                Some(&RangeMapping::from_str("52 50 48").unwrap())
+            )],
+            steps[0].1
+        );
+        let mapping = PiecewiseMapping::compose_layers(&layers);
+        let min_location = steps[6].1.iter().map(|(rng, _)| rng.start as u64).min();
+        assert_eq!((55..68).map(|x| mapping.apply(x)).min(), min_location);
+
+        // The pieces of the last step cover as many values as the seed range
+        let covered: u128 = steps[6].1.iter().map(|(rng, _)| rng.end - rng.start).sum();
+        assert_eq!(13, covered);
+
+        let output = format_seed_range_trace(&Range { start: 55, end: 68 }, &steps);
+        assert!(output.starts_with("seeds 55..68\n  soil 57..70 via 50..98 -> 52..100\n"));
+    }
+
+    fn options(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_day05_seeds_for_location_handler() -> Result<(), AlmanacError> {
+        let report = day05_seeds_for_location_handler(
+            &mut sample_data().into_iter(),
+            &options(&["--location", "46,35, 82"]),
+        )?;
+        assert_eq!(
+            vec![
+                "location 46: seeds 82",
+                "location 35: seeds 13",
+                "location 82: seeds 79"
+            ],
+            report
+        );
+
+        // Not one to one, so every preimage is listed
+        let lines = vec!["seeds: 1 2", "", "seed-to-location map:", "0 10 5"];
+        let report = day05_seeds_for_location_handler(
+            &mut lines.into_iter().map(|x| x.to_string()),
+            &options(&["--location=2,12"]),
+        )?;
+        assert_eq!(
+            vec!["location 2: seeds 2, 12", "location 12: no seeds"],
+            report
+        );
+
+        assert!(day05_seeds_for_location_handler(&mut sample_data().into_iter(), &[]).is_err());
+        assert_eq!(
+            "'x' is not a location",
+            day05_seeds_for_location_handler(
+                &mut sample_data().into_iter(),
+                &options(&["--location", "1,x"])
+            )
+            .unwrap_err()
+            .message
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_day05_handlers_without_seeds() {
+        let lines = ["seeds:", "", "seed-to-location map:", "50 98 2"];
+        let error = "There are no seeds to find a location for";
+        let part1 = day05_part1_handler(&mut lines.iter().map(|x| x.to_string()));
+        assert_eq!(error, part1.unwrap_err().to_string());
+        let part2 = day05_part2_handler(&mut lines.iter().map(|x| x.to_string()));
+        assert_eq!(error, part2.unwrap_err().to_string());
+    }
+
     #[test]
     fn test_day05_part1_handler() {
         let lines = sample_data();
         let calculated = day05_part1_handler(&mut lines.iter().map(|x| x.to_string()));
-        assert_eq!(35, calculated);
+        assert_eq!(Ok(35), calculated);
+    }
+
+    #[test]
+    fn test_day05_part2_handler_near_u64_max() {
+        let lines = vec![
+            "seeds: 0 10".to_string(),
+            "".to_string(),
+            "seed-to-location map:".to_string(),
+            format!("{} 0 10", u64::MAX - 9),
+        ];
+        assert_eq!(
+            Ok(u64::MAX - 9),
+            day05_part2_handler(&mut lines.into_iter())
+        );
+    }
+
+    #[test]
+    fn test_day05_part2_handler_skips_empty_seed_ranges() {
+        let lines = ["seeds: 5 0 100 1", "", "seed-to-location map:", "1000 0 50"];
+        let calculated = day05_part2_handler(&mut lines.iter().map(|x| x.to_string()));
+        assert_eq!(Ok(100), calculated);
+    }
+
+    #[test]
+    fn test_almanac_seed_ranges() {
+        let seeds = |line: &str| Almanac {
+            seeds: Almanac::part1_seed_extractor(line),
+            maps: Vec::new(),
+        };
+        assert_eq!(
+            Ok(vec![
+                Range { start: 79, end: 93 },
+                Range { start: 55, end: 68 }
+            ]),
+            seeds("seeds: 79 14 55 13").seed_ranges()
+        );
+        assert_eq!(
+            Ok(vec![Range {
+                start: 100,
+                end: 101
+            }]),
+            seeds("seeds: 5 0 100 1").seed_ranges()
+        );
+        assert_eq!(
+            Ok(vec![Range {
+                start: u64::MAX as u128 - 4,
+                end: u64::MAX as u128 + 1
+            }]),
+            seeds(&format!("seeds: {} 5", u64::MAX - 4)).seed_ranges()
+        );
+        assert_eq!(
+            "The seeds 18446744073709551615 5 run past the largest u64",
+            seeds(&format!("seeds: {} 5", u64::MAX))
+                .seed_ranges()
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "The seeds should be 'start length' pairs but there are 3 numbers",
+            seeds("seeds: 79 14 55")
+                .seed_ranges()
+                .unwrap_err()
+                .to_string()
+        );
     }
 
     #[test]
     fn test_day05_part2_handler() {
         let lines = sample_data();
         let calculated = day05_part2_handler(&mut lines.iter().map(|x| x.to_string()));
-        assert_eq!(46, calculated);
+        assert_eq!(Ok(46), calculated);
     }
 }
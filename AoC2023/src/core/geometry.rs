@@ -0,0 +1,251 @@
+use std::{fmt, str::FromStr};
+
+use super::math::gcd;
+
+/// A point on the integer lattice. `y` grows downwards like the rows of a puzzle input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn new(x: i64, y: i64) -> Point {
+        Point { x, y }
+    }
+
+    /// Moves `len` steps in the direction
+    pub fn step(&self, direction: Direction, len: i64) -> Point {
+        let (dx, dy) = direction.delta();
+        Point {
+            x: self.x + dx * len,
+            y: self.y + dy * len,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The change in (x, y) for a single step
+    pub fn delta(&self) -> (i64, i64) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseDirectionError {
+    message: String,
+}
+
+impl fmt::Display for ParseDirectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = ParseDirectionError;
+
+    /// Accepts U/D/L/R or the full word in any case
+    fn from_str(input: &str) -> Result<Direction, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "u" | "up" => Ok(Direction::Up),
+            "d" | "down" => Ok(Direction::Down),
+            "l" | "left" => Ok(Direction::Left),
+            "r" | "right" => Ok(Direction::Right),
+            _ => Err(ParseDirectionError {
+                message: format!("'{}' is not a direction", input),
+            }),
+        }
+    }
+}
+
+/// Follows the steps from the start and returns every corner visited, start included.
+pub fn polyline(start: Point, steps: &[(Direction, i64)]) -> Vec<Point> {
+    let mut points = vec![start];
+    let mut current = start;
+    steps.iter().for_each(|(direction, len)| {
+        current = current.step(*direction, *len);
+        points.push(current);
+    });
+    points
+}
+
+/// Shoelace formula. Returns twice the enclosed area so the answer stays exact for
+/// polygons with a half unit area. The polygon is closed from the last vertex back to the first.
+pub fn twice_area(vertices: &[Point]) -> i128 {
+    let sum: i128 = edges(vertices)
+        .map(|(a, b)| a.x as i128 * b.y as i128 - b.x as i128 * a.y as i128)
+        .sum();
+    sum.abs()
+}
+
+/// Number of lattice points on the edges of the closed polygon
+pub fn boundary_points(vertices: &[Point]) -> i128 {
+    edges(vertices)
+        .map(|(a, b)| gcd((b.x - a.x).unsigned_abs(), (b.y - a.y).unsigned_abs()) as i128)
+        .sum()
+}
+
+/// Number of lattice points strictly inside the closed polygon, using Pick's theorem
+/// `A = I + B / 2 - 1`.
+pub fn interior_points(vertices: &[Point]) -> i128 {
+    (twice_area(vertices) - boundary_points(vertices) + 2) / 2
+}
+
+/// Number of lattice points inside or on the edge of the closed polygon.
+/// For a loop dug one cell wide this is the number of cells dug out.
+pub fn enclosed_points(vertices: &[Point]) -> i128 {
+    interior_points(vertices) + boundary_points(vertices)
+}
+
+/// Pairs each vertex with the next, wrapping the last back to the first
+fn edges(vertices: &[Point]) -> impl Iterator<Item = (&Point, &Point)> {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .take(vertices.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle() -> Vec<Point> {
+        vec![
+            Point::new(0, 0),
+            Point::new(4, 0),
+            Point::new(4, 3),
+            Point::new(0, 3),
+        ]
+    }
+
+    #[test]
+    fn test_direction_from_str() {
+        assert_eq!(Ok(Direction::Up), Direction::from_str("U"));
+        assert_eq!(Ok(Direction::Right), Direction::from_str(" right "));
+        assert!(Direction::from_str("north").is_err());
+    }
+
+    #[test]
+    fn test_polyline() {
+        let steps = vec![
+            (Direction::Right, 4),
+            (Direction::Down, 3),
+            (Direction::Left, 4),
+            (Direction::Up, 3),
+        ];
+        let points = polyline(Point::new(0, 0), &steps);
+        assert_eq!(5, points.len());
+        assert_eq!(rectangle(), points[..4]);
+        assert_eq!(Point::new(0, 0), points[4]);
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let points = rectangle();
+        assert_eq!(24, twice_area(&points));
+        assert_eq!(14, boundary_points(&points));
+        assert_eq!(6, interior_points(&points));
+        assert_eq!(20, enclosed_points(&points));
+
+        // Winding direction and a repeated closing vertex do not change anything
+        let mut reversed = points.clone();
+        reversed.reverse();
+        reversed.push(reversed[0]);
+        assert_eq!(24, twice_area(&reversed));
+        assert_eq!(14, boundary_points(&reversed));
+        assert_eq!(6, interior_points(&reversed));
+    }
+
+    #[test]
+    fn test_triangle() {
+        // Diagonal edge crosses lattice points at (1, 3), (2, 2), (3, 1)
+        let points = vec![Point::new(0, 0), Point::new(4, 0), Point::new(0, 4)];
+        assert_eq!(16, twice_area(&points));
+        assert_eq!(12, boundary_points(&points));
+        assert_eq!(3, interior_points(&points));
+
+        // Area of a half unit
+        let points = vec![Point::new(0, 0), Point::new(1, 0), Point::new(0, 1)];
+        assert_eq!(1, twice_area(&points));
+        assert_eq!(0, interior_points(&points));
+    }
+
+    #[test]
+    fn test_l_shape() {
+        // A 4 x 2 block with a 2 x 1 block below its left half
+        let steps: Vec<(Direction, i64)> = "R 4
+D 2
+L 2
+D 1
+L 2
+U 3"
+        .split('\n')
+        .map(|line| {
+            let mut splits = line.split(' ');
+            let direction = Direction::from_str(splits.next().unwrap()).unwrap();
+            let len = splits.next().unwrap().parse::<i64>().unwrap();
+            (direction, len)
+        })
+        .collect();
+        let points = polyline(Point::new(0, 0), &steps);
+        assert_eq!(2 * (4 * 2 + 2), twice_area(&points));
+        assert_eq!(14, boundary_points(&points));
+        assert_eq!(4, interior_points(&points));
+        assert_eq!(18, enclosed_points(&points));
+    }
+
+    #[test]
+    fn test_dig_plan() {
+        let steps = vec![
+            (Direction::Right, 6),
+            (Direction::Down, 5),
+            (Direction::Left, 2),
+            (Direction::Down, 2),
+            (Direction::Right, 2),
+            (Direction::Down, 2),
+            (Direction::Left, 5),
+            (Direction::Up, 2),
+            (Direction::Left, 1),
+            (Direction::Up, 2),
+            (Direction::Right, 2),
+            (Direction::Up, 3),
+            (Direction::Left, 2),
+            (Direction::Up, 2),
+        ];
+        let points = polyline(Point::new(0, 0), &steps);
+        assert_eq!(62, enclosed_points(&points));
+    }
+
+    #[test]
+    fn test_large_coordinates() {
+        // The area overflows an i64 but not the i128 used by the shoelace sum
+        let side = 4_000_000_000i64;
+        let points = vec![
+            Point::new(0, 0),
+            Point::new(side, 0),
+            Point::new(side, side),
+            Point::new(0, side),
+        ];
+        let expected = 2 * (side as i128) * (side as i128);
+        assert_eq!(expected, twice_area(&points));
+        assert_eq!(4 * side as i128, boundary_points(&points));
+        assert_eq!(
+            (side as i128 - 1) * (side as i128 - 1),
+            interior_points(&points)
+        );
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// The outcome of a search from a single start node. Holds the cost to reach
+/// every visited node and the predecessor links needed to rebuild paths.
+#[derive(Debug, Clone)]
+pub struct SearchResult<N> {
+    distances: HashMap<N, u64>,
+    predecessors: HashMap<N, N>,
+}
+
+impl<N> SearchResult<N>
+where
+    N: Clone + Eq + Hash,
+{
+    /// Returns the cost to reach the node, if it was reached at all
+    pub fn distance_to(&self, node: &N) -> Option<u64> {
+        self.distances.get(node).copied()
+    }
+
+    /// Returns every reached node with its cost
+    pub fn distances(&self) -> &HashMap<N, u64> {
+        &self.distances
+    }
+
+    /// Walks the predecessor links back to the start and returns the path
+    /// from the start to the node, both ends included.
+    pub fn path_to(&self, node: &N) -> Option<Vec<N>> {
+        if !self.distances.contains_key(node) {
+            return None;
+        }
+        Some(walk_predecessors(&self.predecessors, node))
+    }
+}
+
+/// Breadth first search where every edge costs 1.
+/// `neighbours` is called with a node and returns the nodes reachable from it.
+pub fn bfs<N, F, I>(start: N, mut neighbours: F) -> SearchResult<N>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut distances: HashMap<N, u64> = HashMap::new();
+    let mut predecessors: HashMap<N, N> = HashMap::new();
+    let mut queue: VecDeque<N> = VecDeque::new();
+
+    distances.insert(start.clone(), 0);
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        for next in neighbours(&node) {
+            if distances.contains_key(&next) {
+                continue;
+            }
+            distances.insert(next.clone(), distance + 1);
+            predecessors.insert(next.clone(), node.clone());
+            queue.push_back(next);
+        }
+    }
+    SearchResult {
+        distances,
+        predecessors,
+    }
+}
+
+/// Dijkstra's shortest path over non-negative edge weights.
+/// `neighbours` is called with a node and returns `(node, cost)` pairs.
+pub fn dijkstra<N, F, I>(start: N, neighbours: F) -> SearchResult<N>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let (distances, predecessors) = best_first(start, neighbours, |_| 0, |_| false);
+    SearchResult {
+        distances,
+        predecessors,
+    }
+}
+
+/// A* search from the start until `is_goal` is satisfied.
+/// `heuristic` must never overestimate the remaining cost or the answer may not be the cheapest.
+/// Returns the cost and the path to the first goal reached.
+pub fn astar<N, F, I, H, G>(
+    start: N,
+    neighbours: F,
+    heuristic: H,
+    mut is_goal: G,
+) -> Option<(u64, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, u64)>,
+    H: FnMut(&N) -> u64,
+    G: FnMut(&N) -> bool,
+{
+    let mut goal: Option<N> = None;
+    let (distances, predecessors) = best_first(start, neighbours, heuristic, |node| {
+        if is_goal(node) {
+            goal = Some(node.clone());
+            return true;
+        }
+        false
+    });
+    let goal = goal?;
+    Some((distances[&goal], walk_predecessors(&predecessors, &goal)))
+}
+
+/// Shared priority queue loop for Dijkstra and A*. Nodes are kept in a Vec and the
+/// heap holds indices into it, so callers don't have to implement `Ord` for their nodes.
+/// Stops early once `stop` returns true for a settled node.
+fn best_first<N, F, I, H, S>(
+    start: N,
+    mut neighbours: F,
+    mut heuristic: H,
+    mut stop: S,
+) -> (HashMap<N, u64>, HashMap<N, N>)
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, u64)>,
+    H: FnMut(&N) -> u64,
+    S: FnMut(&N) -> bool,
+{
+    let mut distances: HashMap<N, u64> = HashMap::new();
+    let mut predecessors: HashMap<N, N> = HashMap::new();
+    let mut pending: Vec<N> = Vec::new();
+    let mut heap: BinaryHeap<Reverse<(u64, u64, usize)>> = BinaryHeap::new();
+
+    distances.insert(start.clone(), 0);
+    heap.push(Reverse((heuristic(&start), 0, 0)));
+    pending.push(start);
+
+    while let Some(Reverse((_estimate, distance, idx))) = heap.pop() {
+        let node = pending[idx].clone();
+        // A cheaper route to this node was already settled
+        if distances.get(&node).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        if stop(&node) {
+            break;
+        }
+        for (next, cost) in neighbours(&node) {
+            let next_distance = distance + cost;
+            if distances
+                .get(&next)
+                .is_some_and(|best| *best <= next_distance)
+            {
+                continue;
+            }
+            distances.insert(next.clone(), next_distance);
+            predecessors.insert(next.clone(), node.clone());
+            heap.push(Reverse((
+                next_distance + heuristic(&next),
+                next_distance,
+                pending.len(),
+            )));
+            pending.push(next);
+        }
+    }
+    (distances, predecessors)
+}
+
+/// Follows the predecessor links from the node back to the start and returns them in start to node order
+fn walk_predecessors<N>(predecessors: &HashMap<N, N>, node: &N) -> Vec<N>
+where
+    N: Clone + Eq + Hash,
+{
+    let mut path = vec![node.clone()];
+    let mut current = node;
+    while let Some(prev) = predecessors.get(current) {
+        path.push(prev.clone());
+        current = prev;
+    }
+    path.reverse();
+    path
+}
+
+/// Disjoint-set forest over the nodes `0..len` with path compression and union by rank.
+#[derive(Debug, Clone)]
+pub struct DisjointSet {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+    sizes: Vec<usize>,
+    components: usize,
+}
+
+impl DisjointSet {
+    /// Every node starts in its own set
+    pub fn new(len: usize) -> DisjointSet {
+        DisjointSet {
+            parents: (0..len).collect(),
+            ranks: vec![0; len],
+            sizes: vec![1; len],
+            components: len,
+        }
+    }
+
+    /// Returns the representative of the node's set, flattening the path on the way
+    pub fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut current = node;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    /// Merges the sets holding `a` and `b`. Returns false if they were already joined.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (parent, child) = if self.ranks[a] < self.ranks[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parents[child] = parent;
+        self.sizes[parent] += self.sizes[child];
+        if self.ranks[parent] == self.ranks[child] {
+            self.ranks[parent] += 1;
+        }
+        self.components -= 1;
+        true
+    }
+
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Number of nodes in the set holding the node
+    pub fn set_size(&mut self, node: usize) -> usize {
+        let root = self.find(node);
+        self.sizes[root]
+    }
+
+    /// Number of disjoint sets remaining
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+}
+
+/// Labels each node in `0..node_count` with its connected component.
+/// Labels are numbered from 0 in order of the lowest node in each component.
+pub fn connected_components(node_count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut sets = DisjointSet::new(node_count);
+    edges.iter().for_each(|(a, b)| {
+        sets.union(*a, *b);
+    });
+
+    let mut labels_by_root: HashMap<usize, usize> = HashMap::new();
+    (0..node_count)
+        .map(|node| {
+            let root = sets.find(node);
+            let next_label = labels_by_root.len();
+            *labels_by_root.entry(root).or_insert(next_label)
+        })
+        .collect()
+}
+
+/// The lightest set of edges that splits a graph in two
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinCut {
+    /// Total weight of the edges crossing the cut
+    pub weight: u64,
+    /// The nodes on one side of the cut, sorted. Every other node is on the other side.
+    pub partition: Vec<usize>,
+}
+
+/// Stoer–Wagner minimum cut of an undirected weighted graph over the nodes `0..node_count`.
+/// Parallel edges add their weights and self loops are ignored. Runs in O(V^3), so it is
+/// meant for graphs of a few thousand nodes at most. Returns None with fewer than two nodes.
+pub fn minimum_cut(node_count: usize, edges: &[(usize, usize, u64)]) -> Option<MinCut> {
+    if node_count < 2 {
+        return None;
+    }
+    let mut weights = vec![vec![0u64; node_count]; node_count];
+    edges
+        .iter()
+        .filter(|(a, b, _)| a != b)
+        .for_each(|(a, b, w)| {
+            weights[*a][*b] += w;
+            weights[*b][*a] += w;
+        });
+
+    // The original nodes that have been merged into each remaining node
+    let mut merged: Vec<Vec<usize>> = (0..node_count).map(|x| vec![x]).collect();
+    let mut active: Vec<usize> = (0..node_count).collect();
+    let mut best: Option<MinCut> = None;
+
+    while active.len() > 1 {
+        // Maximum adjacency ordering: repeatedly add the node most tightly connected to the set
+        let mut connection: Vec<u64> = vec![0; node_count];
+        let mut added = vec![false; node_count];
+        let mut prev = active[0];
+        let mut last = active[0];
+        for _ in 0..active.len() {
+            let next = *active
+                .iter()
+                .filter(|x| !added[**x])
+                .max_by_key(|x| connection[**x])
+                .expect("There should be a node left to add");
+            added[next] = true;
+            prev = last;
+            last = next;
+            active
+                .iter()
+                .for_each(|x| connection[*x] += weights[next][*x]);
+        }
+
+        // The cut of the phase separates the last node added from everything else
+        let weight = connection[last];
+        if best.as_ref().is_none_or(|x| weight < x.weight) {
+            let mut partition = merged[last].clone();
+            partition.sort();
+            best = Some(MinCut { weight, partition });
+        }
+
+        // Merge the last node into the one added before it
+        let moved = std::mem::take(&mut merged[last]);
+        merged[prev].extend(moved);
+        active.iter().for_each(|x| {
+            weights[prev][*x] += weights[last][*x];
+            weights[*x][prev] = weights[prev][*x];
+        });
+        weights[prev][prev] = 0;
+        active.retain(|x| *x != last);
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small maze where '#' is a wall, 'S' is the start and 'E' is the end
+    fn maze() -> Vec<Vec<char>> {
+        "#########
+#S..#...#
+#.#.#.#.#
+#.#...#.#
+#.#####.#
+#......E#
+#########"
+            .split('\n')
+            .map(|x| x.chars().collect())
+            .collect()
+    }
+
+    fn find(grid: &[Vec<char>], c: char) -> (usize, usize) {
+        grid.iter()
+            .enumerate()
+            .find_map(|(row, line)| line.iter().position(|x| *x == c).map(|col| (row, col)))
+            .expect("Character should be in the maze")
+    }
+
+    fn open_neighbours(grid: &[Vec<char>], (row, col): (usize, usize)) -> Vec<(usize, usize)> {
+        [
+            (row - 1, col),
+            (row + 1, col),
+            (row, col - 1),
+            (row, col + 1),
+        ]
+        .into_iter()
+        .filter(|(r, c)| grid[*r][*c] != '#')
+        .collect()
+    }
+
+    #[test]
+    fn test_bfs() {
+        let grid = maze();
+        let start = find(&grid, 'S');
+        let end = find(&grid, 'E');
+        let result = bfs(start, |pos| open_neighbours(&grid, *pos));
+
+        assert_eq!(Some(10), result.distance_to(&end));
+        assert_eq!(Some(0), result.distance_to(&start));
+        assert_eq!(None, result.distance_to(&(0, 0)));
+
+        let path = result.path_to(&end).unwrap();
+        assert_eq!(11, path.len());
+        assert_eq!(start, path[0]);
+        assert_eq!(end, path[10]);
+        // Every step is a single move
+        assert!(path
+            .windows(2)
+            .all(|w| w[0].0.abs_diff(w[1].0) + w[0].1.abs_diff(w[1].1) == 1));
+    }
+
+    #[test]
+    fn test_bfs_unreachable() {
+        let grid: Vec<Vec<char>> = "#####\n#S#E#\n#####"
+            .split('\n')
+            .map(|x| x.chars().collect())
+            .collect();
+        let result = bfs(find(&grid, 'S'), |pos| open_neighbours(&grid, *pos));
+        assert_eq!(None, result.path_to(&find(&grid, 'E')));
+        assert_eq!(1, result.distances().len());
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // 0 -> 1 costs 7 directly but 4 via 2
+        let edges: HashMap<u32, Vec<(u32, u64)>> = HashMap::from([
+            (0, vec![(1, 7), (2, 1)]),
+            (2, vec![(1, 3), (3, 10)]),
+            (1, vec![(3, 2)]),
+        ]);
+        let result = dijkstra(0u32, |node| edges.get(node).cloned().unwrap_or_default());
+        assert_eq!(Some(4), result.distance_to(&1));
+        assert_eq!(Some(6), result.distance_to(&3));
+        assert_eq!(Some(vec![0, 2, 1, 3]), result.path_to(&3));
+    }
+
+    #[test]
+    fn test_dijkstra_weighted_maze() {
+        // Digits are the cost of entering the cell
+        let grid: Vec<Vec<u64>> = "1163
+1381
+2136"
+            .split('\n')
+            .map(|x| x.chars().map(|c| c.to_digit(10).unwrap() as u64).collect())
+            .collect();
+        let result = dijkstra((0usize, 0usize), |(row, col)| {
+            let mut next = Vec::new();
+            if *row > 0 {
+                next.push((*row - 1, *col));
+            }
+            if *col > 0 {
+                next.push((*row, *col - 1));
+            }
+            if *row + 1 < grid.len() {
+                next.push((*row + 1, *col));
+            }
+            if *col + 1 < grid[0].len() {
+                next.push((*row, *col + 1));
+            }
+            next.into_iter()
+                .map(|(r, c)| ((r, c), grid[r][c]))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(Some(13), result.distance_to(&(2, 3)));
+    }
+
+    #[test]
+    fn test_astar() {
+        let grid = maze();
+        let start = find(&grid, 'S');
+        let end = find(&grid, 'E');
+        let (cost, path) = astar(
+            start,
+            |pos| {
+                open_neighbours(&grid, *pos)
+                    .into_iter()
+                    .map(|x| (x, 1))
+                    .collect::<Vec<_>>()
+            },
+            |pos| (pos.0.abs_diff(end.0) + pos.1.abs_diff(end.1)) as u64,
+            |pos| *pos == end,
+        )
+        .unwrap();
+        assert_eq!(10, cost);
+        assert_eq!(11, path.len());
+        assert_eq!(Some(&end), path.last());
+    }
+
+    #[test]
+    fn test_astar_no_goal() {
+        let result = astar(
+            0u32,
+            |node| {
+                if *node < 5 {
+                    vec![(node + 1, 1)]
+                } else {
+                    vec![]
+                }
+            },
+            |_| 0,
+            |node| *node == 10,
+        );
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn test_disjoint_set() {
+        let mut sets = DisjointSet::new(6);
+        assert_eq!(6, sets.component_count());
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+        assert_eq!(3, sets.component_count());
+        assert!(sets.same_set(0, 3));
+        assert!(!sets.same_set(0, 4));
+        assert_eq!(4, sets.set_size(2));
+        assert_eq!(1, sets.set_size(5));
+    }
+
+    #[test]
+    fn test_disjoint_set_long_chain() {
+        let mut sets = DisjointSet::new(10_000);
+        (1..10_000).for_each(|x| {
+            sets.union(x - 1, x);
+        });
+        assert_eq!(1, sets.component_count());
+        assert_eq!(10_000, sets.set_size(0));
+        assert!(sets.same_set(0, 9_999));
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let edges = vec![(0, 1), (1, 2), (3, 4), (6, 5)];
+        assert_eq!(
+            vec![0, 0, 0, 1, 1, 2, 2, 3],
+            connected_components(8, &edges)
+        );
+        assert!(connected_components(0, &[]).is_empty());
+    }
+
+    /// Maps the names in a wiring diagram to node indices and returns the node count and edges
+    fn wiring_diagram(input: &str) -> (usize, Vec<(usize, usize, u64)>) {
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        let mut edges = Vec::new();
+        input.split('\n').for_each(|line| {
+            let mut splits = line.split(':');
+            let lhs = splits.next().unwrap().trim();
+            let next_id = ids.len();
+            let a = *ids.entry(lhs).or_insert(next_id);
+            splits
+                .next()
+                .unwrap()
+                .split(' ')
+                .filter(|x| !x.is_empty())
+                .for_each(|rhs| {
+                    let next_id = ids.len();
+                    let b = *ids.entry(rhs).or_insert(next_id);
+                    edges.push((a, b, 1));
+                });
+        });
+        (ids.len(), edges)
+    }
+
+    #[test]
+    fn test_minimum_cut_wiring() {
+        let (node_count, edges) = wiring_diagram(
+            "jqt: rhn xhk nvd
+rsh: frs pzl lsr
+xhk: hfx
+cmg: qnr nvd lhk bvb
+rhn: xhk bvb hfx
+bvb: xhk hfx
+pzl: lsr hfx nvd
+qnr: nvd
+ntq: jqt hfx bvb xhk
+nvd: lhk
+lsr: lhk
+rzs: qnr cmg lsr rsh
+frs: qnr lhk lsr",
+        );
+        let cut = minimum_cut(node_count, &edges).unwrap();
+        assert_eq!(3, cut.weight);
+        let other_side = node_count - cut.partition.len();
+        assert_eq!(54, cut.partition.len() * other_side);
+    }
+
+    #[test]
+    fn test_minimum_cut_weighted() {
+        // Two triangles joined by a light bridge, plus a heavy parallel edge inside one
+        let edges = vec![
+            (0, 1, 3),
+            (1, 2, 3),
+            (2, 0, 3),
+            (3, 4, 3),
+            (4, 5, 3),
+            (5, 3, 3),
+            (2, 3, 1),
+            (0, 5, 1),
+            (0, 1, 5),
+            (4, 4, 100),
+        ];
+        let cut = minimum_cut(6, &edges).unwrap();
+        assert_eq!(2, cut.weight);
+        assert!(cut.partition == vec![0, 1, 2] || cut.partition == vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_minimum_cut_disconnected() {
+        let edges = vec![(0, 1, 4), (2, 3, 4)];
+        let cut = minimum_cut(4, &edges).unwrap();
+        assert_eq!(0, cut.weight);
+        assert_eq!(None, minimum_cut(1, &[]));
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+/// A JSON value for writing machine readable reports. Objects keep their keys in insertion
+/// order so the output is stable. Display writes compact JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    /// Written as null when not finite, since JSON has no NaN or infinity
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from key and value pairs
+    pub fn object<K: Into<String>>(entries: impl IntoIterator<Item = (K, Json)>) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: impl Into<String>) -> Json {
+        Json::String(value.into())
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Json {
+        Json::Integer(value as i128)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(value: u64) -> Json {
+        Json::Integer(value as i128)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Integer(value as i128)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Json {
+        Json::Integer(value as i128)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Json {
+        Json::Float(value)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Integer(value) => write!(f, "{}", value),
+            Json::Float(value) if value.is_finite() => write!(f, "{}", value),
+            Json::Float(_) => write!(f, "null"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in entries.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Writes a quoted string, escaping quotes, backslashes and control characters
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!("null", Json::Null.to_string());
+        assert_eq!("true", Json::Bool(true).to_string());
+        assert_eq!("-42", Json::from(-42i64).to_string());
+        assert_eq!("18446744073709551615", Json::from(u64::MAX).to_string());
+        assert_eq!("2.5", Json::from(2.5).to_string());
+        assert_eq!("3", Json::from(3.0).to_string());
+        assert_eq!("null", Json::from(f64::NAN).to_string());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(
+            r#""say \"hi\"\\n\n\t\u0001é""#,
+            Json::string("say \"hi\"\\n\n\t\u{1}é").to_string()
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let value = Json::object([
+            ("name", Json::string("red")),
+            ("counts", Json::from(vec![1u32, 2, 3])),
+            ("empty", Json::Array(vec![])),
+            ("nested", Json::object([("ok", Json::Bool(false))])),
+        ]);
+        assert_eq!(
+            r#"{"name":"red","counts":[1,2,3],"empty":[],"nested":{"ok":false}}"#,
+            value.to_string()
+        );
+    }
+}
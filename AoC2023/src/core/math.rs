@@ -0,0 +1,236 @@
+/// Greatest common divisor of two values. `gcd(0, 0)` is 0.
+pub fn gcd(a: u64, b: u64) -> u64 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple of two values, None if the result does not fit into a u64.
+/// `lcm(0, x)` is 0.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Greatest common divisor of every value in the slice. An empty slice returns 0.
+pub fn gcd_slice(values: &[u64]) -> u64 {
+    values.iter().fold(0, |acc, x| gcd(acc, *x))
+}
+
+/// Least common multiple of every value in the slice, None on overflow.
+/// An empty slice returns 1.
+pub fn lcm_slice(values: &[u64]) -> Option<u64> {
+    values.iter().try_fold(1, |acc, x| lcm(acc, *x))
+}
+
+/// Extended Euclidean algorithm.
+/// Returns `(g, x, y)` where `g = gcd(a, b)` is non-negative and `a * x + b * y = g`.
+/// None when a result does not fit into an i64, e.g. `gcd(i64::MIN, 0)` is 2^63.
+pub fn extended_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    let (g, x, y) = extended_gcd_i128(a as i128, b as i128);
+    Some((
+        i64::try_from(g).ok()?,
+        i64::try_from(x).ok()?,
+        i64::try_from(y).ok()?,
+    ))
+}
+
+fn extended_gcd_i128(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    let (mut old_t, mut t) = (0i128, 1i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+        (old_t, t) = (t, old_t - quotient * t);
+    }
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// Returns `x` in `0..modulus` with `a * x ≡ 1 (mod modulus)`, None when `a` and the modulus
+/// are not coprime or the modulus is 0.
+pub fn mod_inverse(a: i64, modulus: u64) -> Option<u64> {
+    if modulus == 0 {
+        return None;
+    }
+    let m = modulus as i128;
+    let (g, x, _) = extended_gcd_i128((a as i128).rem_euclid(m), m);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m) as u64)
+}
+
+/// Computes `base^exp mod modulus` by repeated squaring. Intermediate products are kept in
+/// u128 so any u64 modulus is safe. Panics if the modulus is 0.
+pub fn mod_pow(base: u64, exp: u64, modulus: u64) -> u64 {
+    assert!(modulus != 0, "Modulus should not be zero");
+    let m = modulus as u128;
+    let mut result = 1 % m;
+    let mut base = base as u128 % m;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+    result as u64
+}
+
+/// Chinese remainder theorem over `(residue, modulus)` pairs.
+/// Moduli do not need to be coprime. Returns `(x, m)` where `x` is the smallest non-negative
+/// solution and `m` is the lcm of the moduli, so every solution is `x + k * m`.
+/// Returns None when the congruences contradict each other, a modulus is 0, or the lcm
+/// does not fit into a u64.
+pub fn crt(congruences: &[(u64, u64)]) -> Option<(u64, u64)> {
+    congruences
+        .iter()
+        .try_fold((0u64, 1u64), |(x, m), (residue, modulus)| {
+            merge_congruence(x, m, *residue, *modulus)
+        })
+}
+
+/// Combines `x ≡ a (mod m)` and `x ≡ b (mod n)` into a single congruence
+fn merge_congruence(a: u64, m: u64, b: u64, n: u64) -> Option<(u64, u64)> {
+    if n == 0 {
+        return None;
+    }
+    let (a, m, b, n) = (a as i128, m as i128, (b % n) as i128, n as i128);
+    let (g, p, _) = extended_gcd_i128(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+    let lcm = (m / g).checked_mul(n).filter(|x| *x <= u64::MAX as i128)?;
+    // a + m * k ≡ b (mod n)  =>  k ≡ (b - a) / g * p (mod n / g)
+    // Both factors are below `n / g`, so the product fits into a u128
+    let step = n / g;
+    let k = ((b - a) / g).rem_euclid(step) as u128 * p.rem_euclid(step) as u128 % step as u128;
+    let x = (a + m * k as i128).rem_euclid(lcm);
+    Some((x as u64, lcm as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(6, gcd(12, 18));
+        assert_eq!(6, gcd(18, 12));
+        assert_eq!(1, gcd(17, 5));
+        assert_eq!(7, gcd(0, 7));
+        assert_eq!(7, gcd(7, 0));
+        assert_eq!(0, gcd(0, 0));
+        assert_eq!(u64::MAX, gcd(u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(Some(36), lcm(12, 18));
+        assert_eq!(Some(0), lcm(0, 18));
+        assert_eq!(Some(u64::MAX), lcm(u64::MAX, 1));
+        assert_eq!(None, lcm(u64::MAX, u64::MAX - 1));
+    }
+
+    #[test]
+    fn test_gcd_slice() {
+        assert_eq!(0, gcd_slice(&[]));
+        assert_eq!(4, gcd_slice(&[8, 12, 20]));
+        assert_eq!(1, gcd_slice(&[8, 12, 21]));
+    }
+
+    #[test]
+    fn test_lcm_slice() {
+        assert_eq!(Some(1), lcm_slice(&[]));
+        assert_eq!(Some(6), lcm_slice(&[2, 3]));
+        // Cycle lengths from a typical ghost-walking puzzle
+        assert_eq!(
+            Some(8_906_539_031_197),
+            lcm_slice(&[11_309, 19_199, 12_361, 16_043, 13_939, 18_673])
+        );
+        assert_eq!(None, lcm_slice(&[u64::MAX, 2]));
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        let (g, x, y) = extended_gcd(240, 46).unwrap();
+        assert_eq!(2, g);
+        assert_eq!(2, 240 * x + 46 * y);
+
+        let (g, x, y) = extended_gcd(-240, 46).unwrap();
+        assert_eq!(2, g);
+        assert_eq!(2, -240 * x + 46 * y);
+
+        assert_eq!(Some((5, 1, 0)), extended_gcd(5, 0));
+        assert_eq!(Some((0, 1, 0)), extended_gcd(0, 0));
+        assert_eq!(None, extended_gcd(i64::MIN, 0));
+        assert_eq!(None, extended_gcd(i64::MIN, i64::MIN));
+
+        let (g, x, y) = extended_gcd(i64::MIN, 6).unwrap();
+        assert_eq!(2, g);
+        assert_eq!(2, i64::MIN as i128 * x as i128 + 6 * y as i128);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(Some(4), mod_inverse(3, 11));
+        assert_eq!(Some(7), mod_inverse(-3, 11));
+        assert_eq!(None, mod_inverse(6, 9));
+        assert_eq!(None, mod_inverse(3, 0));
+        assert_eq!(Some(0), mod_inverse(5, 1));
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        assert_eq!(24, mod_pow(2, 10, 1000));
+        assert_eq!(1, mod_pow(7, 0, 13));
+        assert_eq!(0, mod_pow(7, 0, 1));
+        assert_eq!(0, mod_pow(0, 5, 13));
+        // Fermat's little theorem with a modulus close to u64::MAX
+        let p = 18_446_744_073_709_551_557u64;
+        assert_eq!(1, mod_pow(123_456_789, p - 1, p));
+    }
+
+    #[test]
+    fn test_crt_coprime() {
+        // x ≡ 2 (mod 3), x ≡ 3 (mod 5), x ≡ 2 (mod 7)
+        assert_eq!(Some((23, 105)), crt(&[(2, 3), (3, 5), (2, 7)]));
+        assert_eq!(Some((0, 1)), crt(&[]));
+    }
+
+    #[test]
+    fn test_crt_non_coprime() {
+        // x ≡ 2 (mod 4), x ≡ 4 (mod 6)  =>  x ≡ 10 (mod 12)
+        assert_eq!(Some((10, 12)), crt(&[(2, 4), (4, 6)]));
+        // x ≡ 1 (mod 4), x ≡ 2 (mod 6) has no solution
+        assert_eq!(None, crt(&[(1, 4), (2, 6)]));
+        // Residues larger than their modulus are reduced
+        assert_eq!(Some((10, 12)), crt(&[(14, 4), (10, 6)]));
+    }
+
+    #[test]
+    fn test_crt_invalid() {
+        assert_eq!(None, crt(&[(1, 0)]));
+        assert_eq!(None, crt(&[(0, u64::MAX), (0, u64::MAX - 1)]));
+    }
+
+    #[test]
+    fn test_crt_large() {
+        let moduli = [1_000_000_007u64, 1_000_000_009];
+        let x = 123_456_789_012_345_678u64;
+        let congruences: Vec<(u64, u64)> = moduli.iter().map(|m| (x % m, *m)).collect();
+        let (solution, m) = crt(&congruences).unwrap();
+        assert_eq!(x % m, solution);
+    }
+}
@@ -0,0 +1,40 @@
+/// A small xorshift generator, so tests and benchmarks can build large pseudo-random
+/// inputs that are the same on every run
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// The seed must not be 0, which would only ever produce 0
+    pub fn new(seed: u64) -> XorShift {
+        assert_ne!(0, seed, "a xorshift seed cannot be 0");
+        XorShift { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A value below `bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeatable() {
+        let mut a = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut b = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let values: Vec<u64> = (0..100).map(|_| a.below(10)).collect();
+        assert_eq!(values, (0..100).map(|_| b.below(10)).collect::<Vec<u64>>());
+        assert!(values.iter().all(|x| *x < 10));
+        assert!((0..10).all(|x| values.contains(&x)));
+    }
+}
@@ -1 +1,2 @@
 pub mod data_loader;
+pub mod options;
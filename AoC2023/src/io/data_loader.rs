@@ -6,9 +6,17 @@ use std::{
 
 /// Returns an iterator of each line from the provided file name.
 pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+where
+    P: AsRef<Path>,
+{
+    Ok(open_reader(filename)?.lines())
+}
+
+/// Returns a buffered reader over the raw bytes of the provided file name.
+pub fn open_reader<P>(filename: P) -> io::Result<io::BufReader<File>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(io::BufReader::with_capacity(1 << 16, file))
 }
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use super::data_loader;
+
+/// Returns the value given for a flag, either as the next argument (`--bag red=12`)
+/// or joined with an equals sign (`--bag=red=12`).
+pub fn flag_value<'a>(options: &'a [String], flag: &str) -> Option<&'a str> {
+    options.iter().enumerate().find_map(|(idx, option)| {
+        if option == flag {
+            return options.get(idx + 1).map(|x| x.as_str());
+        }
+        option.strip_prefix(flag).and_then(|x| x.strip_prefix('='))
+    })
+}
+
+/// True when the flag is present on its own, e.g. `--json`
+pub fn has_flag(options: &[String], flag: &str) -> bool {
+    options.iter().any(|x| x == flag)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpecError {
+    pub message: String,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Returns the contents of the file named by `file_flag`, or else the value given for `flag`.
+/// None when neither flag is present.
+pub fn read_spec(
+    options: &[String],
+    flag: &str,
+    file_flag: &str,
+) -> Result<Option<String>, SpecError> {
+    if let Some(path) = flag_value(options, file_flag) {
+        let lines = data_loader::read_lines(path).map_err(|x| SpecError {
+            message: format!("could not read '{}': {}", path, x),
+        })?;
+        let contents: Vec<String> = lines.map_while(Result::ok).collect();
+        return Ok(Some(contents.join("\n")));
+    }
+    Ok(flag_value(options, flag).map(|x| x.to_string()))
+}
+
+/// Parses `key=value` entries separated by commas or new lines, trimming both sides.
+/// Blank entries and lines starting with '#' are ignored. `shape` names the parts in
+/// errors, e.g. "word=value".
+pub fn parse_key_values<'a>(
+    spec: &'a str,
+    shape: &str,
+) -> Result<Vec<(&'a str, &'a str)>, SpecError> {
+    spec.split([',', '\n'])
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| SpecError {
+                    message: format!("'{}' should look like {}", entry, shape),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_flag_value() {
+        let args = options(&["--vocab", "words.txt", "--bag=red=12,blue=3", "--json"]);
+        assert_eq!(Some("words.txt"), flag_value(&args, "--vocab"));
+        assert_eq!(Some("red=12,blue=3"), flag_value(&args, "--bag"));
+        assert_eq!(None, flag_value(&args, "--json"));
+        assert_eq!(None, flag_value(&args, "--words"));
+        assert_eq!(None, flag_value(&options(&["--vocab"]), "--vocab"));
+        assert_eq!(None, flag_value(&options(&["--vocabulary=x"]), "--vocab"));
+    }
+
+    #[test]
+    fn test_read_spec() {
+        let args = options(&["--words", "a=1"]);
+        assert_eq!(
+            Ok(Some("a=1".to_string())),
+            read_spec(&args, "--words", "--vocab")
+        );
+        assert_eq!(Ok(None), read_spec(&args, "--bag", "--bag-file"));
+        let args = options(&["--words", "a=1", "--vocab=./does/not/exist.txt"]);
+        assert!(read_spec(&args, "--words", "--vocab").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_values() {
+        assert_eq!(
+            Ok(vec![("red", "12"), ("green", "13"), ("light blue", "")]),
+            parse_key_values(
+                " red = 12 ,green=13\n# comment\n\nlight blue=",
+                "colour=count"
+            )
+        );
+        assert_eq!(Ok(vec![]), parse_key_values("", "colour=count"));
+        assert_eq!(
+            "'red' should look like colour=count",
+            parse_key_values("red", "colour=count")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_has_flag() {
+        let args = options(&["--strict", "--vocab", "words.txt"]);
+        assert!(has_flag(&args, "--strict"));
+        assert!(!has_flag(&args, "--json"));
+    }
+}
@@ -7,11 +7,23 @@ use crate::core::day04;
 use crate::core::day05;
 use crate::io::data_loader;
 use std::env;
+use std::io::BufRead;
 use std::path::Path;
+use std::process;
+
+/// How a scenario wants its input. A scenario prints its answer and returns an error
+/// message when it cannot produce one.
+enum Process {
+    Lines(fn(&mut dyn Iterator<Item = String>) -> Result<(), String>),
+    /// Also receives the command line arguments that follow the scenario name
+    LinesWithOptions(fn(&mut dyn Iterator<Item = String>, &[String]) -> Result<(), String>),
+    /// Reads the raw bytes itself, for inputs too large to handle line by line as Strings
+    Reader(fn(&mut dyn BufRead, &[String]) -> Result<(), String>),
+}
 
 struct ScenarioConfig {
     file_path: String,
-    process_fn: fn(&mut dyn Iterator<Item = String>) -> (),
+    process_fn: Process,
 }
 
 // Examines the command line arguments and passes back
@@ -19,48 +31,76 @@ struct ScenarioConfig {
 fn select_scenario() -> ScenarioConfig {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        panic!("Expected 1 argument like 'day01_part1'");
+        panic!("Expected at least 1 argument like 'day01_part1'");
     }
     match args[1].as_str() {
         "day01_part1" => ScenarioConfig {
             file_path: "./data/day01.txt".to_string(),
-            process_fn: day01::day01_part1,
+            process_fn: Process::Reader(day01::day01_part1),
         },
         "day01_part2" => ScenarioConfig {
             file_path: "./data/day01.txt".to_string(),
-            process_fn: day01::day01_part2,
+            process_fn: Process::LinesWithOptions(day01::day01_part2),
         },
         "day02_part1" => ScenarioConfig {
             file_path: "./data/day02.txt".to_string(),
-            process_fn: day02::day02_part1,
+            process_fn: Process::LinesWithOptions(day02::day02_part1),
         },
         "day02_part2" => ScenarioConfig {
             file_path: "./data/day02.txt".to_string(),
-            process_fn: day02::day02_part2,
+            process_fn: Process::Lines(day02::day02_part2),
+        },
+        "day02_query" => ScenarioConfig {
+            file_path: "./data/day02.txt".to_string(),
+            process_fn: Process::LinesWithOptions(day02::day02_query),
+        },
+        "day02_stats" => ScenarioConfig {
+            file_path: "./data/day02.txt".to_string(),
+            process_fn: Process::LinesWithOptions(day02::day02_stats),
         },
         "day03_part1" => ScenarioConfig {
             file_path: "./data/day03.txt".to_string(),
-            process_fn: day03::day03_part1,
+            process_fn: Process::LinesWithOptions(day03::day03_part1),
         },
         "day03_part2" => ScenarioConfig {
             file_path: "./data/day03.txt".to_string(),
-            process_fn: day03::day03_part2,
+            process_fn: Process::LinesWithOptions(day03::day03_part2),
+        },
+        "day03_report" => ScenarioConfig {
+            file_path: "./data/day03.txt".to_string(),
+            process_fn: Process::LinesWithOptions(day03::day03_report),
+        },
+        "day03_render" => ScenarioConfig {
+            file_path: "./data/day03.txt".to_string(),
+            process_fn: Process::LinesWithOptions(day03::day03_render),
         },
         "day04_part1" => ScenarioConfig {
             file_path: "./data/day04.txt".to_string(),
-            process_fn: day04::day04_part1,
+            process_fn: Process::Lines(day04::day04_part1),
         },
         "day04_part2" => ScenarioConfig {
             file_path: "./data/day04.txt".to_string(),
-            process_fn: day04::day04_part2,
+            process_fn: Process::Lines(day04::day04_part2),
         },
         "day05_part1" => ScenarioConfig {
             file_path: "./data/day05.txt".to_string(),
-            process_fn: day05::day05_part1,
+            process_fn: Process::Lines(day05::day05_part1),
         },
         "day05_part2" => ScenarioConfig {
             file_path: "./data/day05.txt".to_string(),
-            process_fn: day05::day05_part2,
+            process_fn: Process::Lines(day05::day05_part2),
+        },
+        "day05_part1_trace" => ScenarioConfig {
+            file_path: "./data/day05.txt".to_string(),
+            process_fn: Process::Lines(day05::day05_part1_trace),
+        },
+        "day05_part2_trace" => ScenarioConfig {
+            file_path: "./data/day05.txt".to_string(),
+            process_fn: Process::Lines(day05::day05_part2_trace),
+        },
+        "day05_seeds_for_location" => ScenarioConfig {
+            file_path: "./data/day05.txt".to_string(),
+            process_fn: Process::LinesWithOptions(day05::day05_seeds_for_location),
         },
 
         _ => {
@@ -73,11 +113,25 @@ fn main() {
     // Parse the input arguments
     let scenario = select_scenario();
 
-    // Get the lines iterator
-    let lines = data_loader::read_lines(Path::new(scenario.file_path.as_str()))
-        .unwrap_or_else(|_| panic!("Expected {} to have readable lines.", scenario.file_path));
+    // Get the input reader
+    let mut reader = data_loader::open_reader(Path::new(scenario.file_path.as_str()))
+        .unwrap_or_else(|_| panic!("Expected {} to be readable.", scenario.file_path));
+
+    // Anything after the scenario name is passed along as options
+    let options: Vec<String> = env::args().skip(2).collect();
 
     // Only process the 'Ok()' items
-    let mut itr = lines.map_while(Result::ok);
-    (scenario.process_fn)(&mut itr);
+    let result = match scenario.process_fn {
+        Process::Lines(process_fn) => process_fn(&mut reader.lines().map_while(Result::ok)),
+        Process::LinesWithOptions(process_fn) => {
+            process_fn(&mut reader.lines().map_while(Result::ok), &options)
+        }
+        Process::Reader(process_fn) => process_fn(&mut reader, &options),
+    };
+
+    // A failed scenario reports why and exits with a non-zero status
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
 }